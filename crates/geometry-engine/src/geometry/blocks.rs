@@ -0,0 +1,191 @@
+use std::collections::HashMap;
+use serde::{Deserialize, Serialize};
+use super::voxel::Face;
+
+/// Numeric block type as stored in chunks and region files.
+/// Everything else about a block is looked up in the `BlockRegistry`.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Hash, Serialize, Deserialize)]
+pub struct BlockId(pub u16);
+
+impl BlockId {
+    /// Empty space, reserved in every registry
+    pub const AIR: BlockId = BlockId(0);
+
+    /// Ids outside the `u16` range become air
+    pub fn from_u32(value: u32) -> Self {
+        u16::try_from(value).map_or(BlockId::AIR, BlockId)
+    }
+}
+
+#[derive(Clone, Debug, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct BlockDefinition {
+    pub id: BlockId,
+    pub name: String,
+    /// Flat colour, used for faces without a texture in the current atlas
+    pub color: [f32; 3],
+    /// Atlas tiles of the block's faces
+    #[serde(default)]
+    pub textures: BlockTextures,
+    /// Neighbouring faces stay visible through this block
+    #[serde(default)]
+    pub transparent: bool,
+    /// Stops raycasts that skip non-solid blocks, so water and the like can be looked
+    /// through when picking
+    #[serde(default = "default_solid")]
+    pub solid: bool,
+    /// Light level the block emits, 0 for none
+    #[serde(default)]
+    pub light_emission: u8,
+}
+
+fn default_solid() -> bool {
+    true
+}
+
+impl BlockDefinition {
+    fn air() -> Self {
+        Self {
+            id: BlockId::AIR,
+            name: "air".to_string(),
+            color: [0.0, 0.0, 0.0],
+            textures: BlockTextures::default(),
+            transparent: true,
+            solid: false,
+            light_emission: 0,
+        }
+    }
+
+    pub fn is_air(&self) -> bool {
+        self.id == BlockId::AIR
+    }
+}
+
+/// Atlas tile indices per face, e.g. `{ "top": 1, "side": 2, "bottom": 3 }`.
+/// `all` covers every face that has no entry of its own.
+#[derive(Clone, Debug, Default, Serialize, Deserialize)]
+#[serde(default)]
+pub struct BlockTextures {
+    pub all: Option<u32>,
+    pub top: Option<u32>,
+    pub bottom: Option<u32>,
+    pub side: Option<u32>,
+}
+
+impl BlockTextures {
+    pub fn tile(&self, face: Face) -> Option<u32> {
+        let tile = match face {
+            Face::Top => self.top,
+            Face::Bottom => self.bottom,
+            Face::Left | Face::Right | Face::Front | Face::Back => self.side,
+        };
+        tile.or(self.all)
+    }
+}
+
+/// Blocks the terrain generator places, resolved by name when the registry is loaded
+#[derive(Clone, Copy, Debug)]
+pub struct TerrainBlocks {
+    pub stone: BlockId,
+    pub grass: BlockId,
+    pub dirt: BlockId,
+    pub water: BlockId,
+    pub sand: BlockId,
+    pub wood: BlockId,
+    pub leaves: BlockId,
+    pub snow: BlockId,
+    pub cactus: BlockId,
+}
+
+// Layout shared by the JSON and TOML registry files
+#[derive(Deserialize)]
+struct RegistryFile {
+    blocks: Vec<BlockDefinition>,
+}
+
+/// Block definitions by id. Id 0 is always air; ids without a definition behave as air.
+pub struct BlockRegistry {
+    definitions: Vec<Option<BlockDefinition>>,
+    by_name: HashMap<String, BlockId>,
+    terrain: TerrainBlocks,
+}
+
+impl Default for BlockRegistry {
+    fn default() -> Self {
+        Self::from_json(include_str!("blocks.json")).expect("built-in block registry is valid")
+    }
+}
+
+impl BlockRegistry {
+    /// Parses `{ "blocks": [{ "id": 1, "name": "stone", "color": [0.5, 0.5, 0.5] }, ...] }`
+    pub fn from_json(source: &str) -> Result<Self, String> {
+        let file: RegistryFile = serde_json::from_str(source).map_err(|err| err.to_string())?;
+        Self::from_definitions(file.blocks)
+    }
+
+    /// Parses the same layout as `from_json` written as a TOML `[[blocks]]` array
+    pub fn from_toml(source: &str) -> Result<Self, String> {
+        let file: RegistryFile = toml::from_str(source).map_err(|err| err.to_string())?;
+        Self::from_definitions(file.blocks)
+    }
+
+    fn from_definitions(blocks: Vec<BlockDefinition>) -> Result<Self, String> {
+        let mut definitions = vec![Some(BlockDefinition::air())];
+        let mut by_name = HashMap::from([("air".to_string(), BlockId::AIR)]);
+
+        for block in blocks {
+            if block.id == BlockId::AIR {
+                return Err(format!("block `{}` uses id 0, which is reserved for air", block.name));
+            }
+            if by_name.insert(block.name.clone(), block.id).is_some() {
+                return Err(format!("block name `{}` is defined twice", block.name));
+            }
+            let slot = block.id.0 as usize;
+            if definitions.len() <= slot {
+                definitions.resize(slot + 1, None);
+            }
+            if definitions[slot].is_some() {
+                return Err(format!("block id {} is defined twice", block.id.0));
+            }
+            definitions[slot] = Some(block);
+        }
+
+        let lookup = |name: &str| {
+            by_name.get(name).copied()
+                .ok_or_else(|| format!("block `{}` used by terrain generation is not defined", name))
+        };
+        let terrain = TerrainBlocks {
+            stone: lookup("stone")?,
+            grass: lookup("grass")?,
+            dirt: lookup("dirt")?,
+            water: lookup("water")?,
+            sand: lookup("sand")?,
+            wood: lookup("wood")?,
+            leaves: lookup("leaves")?,
+            snow: lookup("snow")?,
+            cactus: lookup("cactus")?,
+        };
+
+        Ok(Self {
+            definitions,
+            by_name,
+            terrain,
+        })
+    }
+
+    /// Properties of a block, the air definition for unknown ids
+    pub fn get(&self, id: BlockId) -> &BlockDefinition {
+        self.definitions
+            .get(id.0 as usize)
+            .and_then(Option::as_ref)
+            .unwrap_or_else(|| self.definitions[0].as_ref().unwrap())
+    }
+
+    pub fn id(&self, name: &str) -> Option<BlockId> {
+        self.by_name.get(name).copied()
+    }
+
+    pub fn terrain_blocks(&self) -> TerrainBlocks {
+        self.terrain
+    }
+}
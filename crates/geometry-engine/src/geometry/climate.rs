@@ -0,0 +1,205 @@
+// Climate and biomes. Two low frequency noise maps give every world column a temperature
+// and a humidity, and the column's biome is the entry of the biome table whose climate
+// lies closest. Biomes reshape the heightmap through height modifiers that are blended
+// over every biome close in climate, so the ground changes smoothly across biome borders
+// while surface blocks and vegetation switch at the border itself.
+use super::blocks::{BlockId, TerrainBlocks};
+use super::noise;
+use super::terrain::TerrainParams;
+
+// Climate distance over which a neighbouring biome's height modifiers fade out
+const BLEND_DISTANCE: f32 = 0.3;
+
+// Widens the climate noise so the table's outer biomes are reached
+const CLIMATE_CONTRAST: f32 = 1.4;
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub enum Biome {
+    Plains,
+    Forest,
+    Desert,
+    Tundra,
+    Swamp,
+    Savanna,
+    Mountains,
+}
+
+/// What grows on a biome's ground
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Vegetation {
+    Trees,
+    Cacti,
+}
+
+/// Climate a biome is centred on and how it changes the terrain
+#[derive(Clone, Copy, Debug)]
+pub struct BiomeDefinition {
+    pub biome: Biome,
+    /// Temperature the biome is centred on, cold -1 to hot 1
+    pub temperature: f32,
+    /// Humidity the biome is centred on, dry -1 to wet 1
+    pub humidity: f32,
+    /// Multiplier on the relief of the base heightmap
+    pub height_scale: f32,
+    /// Blocks added to the base heightmap
+    pub height_offset: f32,
+    pub vegetation: Vegetation,
+    /// Multiplier on `TerrainParams::tree_density`
+    pub vegetation_density: f32,
+}
+
+/// The biome table, in `Biome` order
+pub const BIOMES: [BiomeDefinition; 7] = [
+    BiomeDefinition {
+        biome: Biome::Plains,
+        temperature: 0.1,
+        humidity: -0.1,
+        height_scale: 0.5,
+        height_offset: 3.0,
+        vegetation: Vegetation::Trees,
+        vegetation_density: 0.3,
+    },
+    BiomeDefinition {
+        biome: Biome::Forest,
+        temperature: 0.0,
+        humidity: 0.5,
+        height_scale: 0.9,
+        height_offset: 4.0,
+        vegetation: Vegetation::Trees,
+        vegetation_density: 3.0,
+    },
+    BiomeDefinition {
+        biome: Biome::Desert,
+        temperature: 0.8,
+        humidity: -0.7,
+        height_scale: 0.6,
+        height_offset: 3.0,
+        vegetation: Vegetation::Cacti,
+        vegetation_density: 0.5,
+    },
+    BiomeDefinition {
+        biome: Biome::Tundra,
+        temperature: -0.8,
+        humidity: 0.1,
+        height_scale: 0.7,
+        height_offset: 2.0,
+        vegetation: Vegetation::Trees,
+        vegetation_density: 0.4,
+    },
+    BiomeDefinition {
+        biome: Biome::Swamp,
+        temperature: 0.4,
+        humidity: 0.8,
+        height_scale: 0.1,
+        height_offset: 0.5,
+        vegetation: Vegetation::Trees,
+        vegetation_density: 1.5,
+    },
+    BiomeDefinition {
+        biome: Biome::Savanna,
+        temperature: 0.6,
+        humidity: -0.1,
+        height_scale: 0.6,
+        height_offset: 2.0,
+        vegetation: Vegetation::Trees,
+        vegetation_density: 0.4,
+    },
+    BiomeDefinition {
+        biome: Biome::Mountains,
+        temperature: -0.4,
+        humidity: -0.6,
+        height_scale: 1.8,
+        height_offset: 12.0,
+        vegetation: Vegetation::Trees,
+        vegetation_density: 0.0,
+    },
+];
+
+/// Highest `vegetation_density` in the table
+pub const MAX_VEGETATION_DENSITY: f32 = 3.0;
+
+impl Biome {
+    pub fn definition(&self) -> &'static BiomeDefinition {
+        &BIOMES[*self as usize]
+    }
+
+    /// Block covering the ground
+    pub fn top_block(&self, blocks: &TerrainBlocks) -> BlockId {
+        match self {
+            Biome::Desert => blocks.sand,
+            Biome::Tundra => blocks.snow,
+            Biome::Mountains => blocks.stone,
+            Biome::Plains | Biome::Forest | Biome::Swamp | Biome::Savanna => blocks.grass,
+        }
+    }
+
+    /// Block of the layers between the top block and the stone
+    pub fn filler_block(&self, blocks: &TerrainBlocks) -> BlockId {
+        match self {
+            Biome::Desert => blocks.sand,
+            Biome::Mountains => blocks.stone,
+            Biome::Plains | Biome::Forest | Biome::Tundra | Biome::Swamp | Biome::Savanna => blocks.dirt,
+        }
+    }
+
+    /// Whether shores in this biome turn to sand
+    pub fn has_beaches(&self) -> bool {
+        *self != Biome::Swamp
+    }
+}
+
+/// Temperature and humidity of a world column, both in [-1, 1]
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Climate {
+    pub temperature: f32,
+    pub humidity: f32,
+}
+
+impl Climate {
+    /// Climate of the column at (x, z), shifted by the `temperature` and `humidity` parameters
+    pub fn at(x: f32, z: f32, params: &TerrainParams) -> Self {
+        let scale = 1.0 / params.biome_scale;
+        let seed = params.seed_hash();
+        let temperature = noise::perlin2d(seed.wrapping_add(4), x * scale, z * scale) * CLIMATE_CONTRAST;
+        // Humidity varies on a different scale so the two maps don't line up
+        let humidity = noise::perlin2d(seed.wrapping_add(8), x * scale * 1.3, z * scale * 1.3) * CLIMATE_CONTRAST;
+        Self {
+            temperature: (temperature + params.temperature).clamp(-1.0, 1.0),
+            humidity: (humidity + params.humidity).clamp(-1.0, 1.0),
+        }
+    }
+
+    fn distance(&self, definition: &BiomeDefinition) -> f32 {
+        let dt = self.temperature - definition.temperature;
+        let dh = self.humidity - definition.humidity;
+        (dt * dt + dh * dh).sqrt()
+    }
+
+    /// The biome whose climate lies closest
+    pub fn biome(&self) -> Biome {
+        BIOMES
+            .iter()
+            .min_by(|a, b| self.distance(a).total_cmp(&self.distance(b)))
+            .map_or(Biome::Plains, |definition| definition.biome)
+    }
+
+    /// Height scale and offset, averaged over the biomes within `BLEND_DISTANCE` of
+    /// the closest one. Weights fall to zero as a biome gets that much further away
+    /// than the closest, so the result is continuous where the closest biome changes.
+    pub fn height_modifiers(&self) -> (f32, f32) {
+        let distances = BIOMES.map(|definition| self.distance(&definition));
+        let nearest = distances.iter().copied().fold(f32::MAX, f32::min);
+
+        let mut total = 0.0;
+        let mut scale = 0.0;
+        let mut offset = 0.0;
+        for (definition, distance) in BIOMES.iter().zip(distances) {
+            let weight = (1.0 - (distance - nearest) / BLEND_DISTANCE).max(0.0);
+            let weight = weight * weight;
+            total += weight;
+            scale += definition.height_scale * weight;
+            offset += definition.height_offset * weight;
+        }
+        (scale / total, offset / total)
+    }
+}
@@ -0,0 +1,63 @@
+use std::collections::HashMap;
+use super::blocks::BlockId;
+use super::voxel::VoxelChunk;
+
+type ChunkPos = (i32, i32, i32);
+type BlockPos = (i32, i32, i32);
+
+/// Player block edits, kept apart from generated terrain so they can be
+/// re-applied whenever a chunk is regenerated or reloaded after eviction
+#[derive(Default)]
+pub struct EditLayer {
+    // Edited blocks keyed by world position, bucketed by chunk for fast re-application
+    chunks: HashMap<ChunkPos, HashMap<BlockPos, BlockId>>,
+}
+
+impl EditLayer {
+    pub fn set(&mut self, world_pos: BlockPos, block: BlockId) {
+        let chunk_pos = (
+            world_pos.0.div_euclid(16),
+            world_pos.1.div_euclid(16),
+            world_pos.2.div_euclid(16),
+        );
+        self.chunks.entry(chunk_pos).or_default().insert(world_pos, block);
+    }
+
+    /// Writes every edit inside the chunk at `chunk_pos` over its generated blocks
+    pub fn apply(&self, chunk_pos: ChunkPos, chunk: &mut VoxelChunk) {
+        let Some(edits) = self.chunks.get(&chunk_pos) else {
+            return;
+        };
+        for (&(x, y, z), &block) in edits {
+            chunk.set_block(
+                x.rem_euclid(16) as usize,
+                y.rem_euclid(16) as usize,
+                z.rem_euclid(16) as usize,
+                block,
+            );
+        }
+    }
+
+    /// Edited positions and blocks inside the chunk at `chunk_pos`
+    pub fn chunk_edits(&self, chunk_pos: ChunkPos) -> impl Iterator<Item = (BlockPos, BlockId)> + '_ {
+        self.chunks.get(&chunk_pos).into_iter().flatten().map(|(&pos, &block)| (pos, block))
+    }
+
+    /// Positions of chunks holding at least one edit
+    pub fn chunk_positions(&self) -> impl Iterator<Item = ChunkPos> + '_ {
+        self.chunks.keys().copied()
+    }
+
+    /// Drops every edit inside the chunk at `chunk_pos`
+    pub fn clear_chunk(&mut self, chunk_pos: ChunkPos) {
+        self.chunks.remove(&chunk_pos);
+    }
+
+    pub fn len(&self) -> usize {
+        self.chunks.values().map(HashMap::len).sum()
+    }
+
+    pub fn clear(&mut self) {
+        self.chunks.clear();
+    }
+}
@@ -0,0 +1,221 @@
+// Second generation phase: features such as trees that are stamped on top of the terrain.
+// Whether and how a feature grows is decided from the seed per world column, never per
+// chunk, and each chunk looks for origins in every column close enough to reach into it.
+// A feature crossing a chunk border is therefore written into all chunks it overlaps,
+// whichever of them is generated first.
+use super::blocks::{BlockId, TerrainBlocks};
+use super::climate::{Vegetation, MAX_VEGETATION_DENSITY};
+use super::noise;
+use super::terrain::TerrainParams;
+use super::voxel::VoxelChunk;
+use super::worldgen::Column;
+
+type ChunkPos = (i32, i32, i32);
+type BlockPos = (i32, i32, i32);
+
+// Furthest any feature extends sideways from its origin column
+const MAX_RADIUS: i32 = 2;
+// Highest block of any feature above its origin
+const MAX_HEIGHT: i32 = 7;
+
+// Seed offset for placement hashes, keeping them apart from the terrain noise layers
+const PLACEMENT_SEED: i32 = 100;
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Feature {
+    /// Wood trunk of `height` blocks under a rounded leaf canopy
+    Tree { height: i32 },
+    /// Column of cactus blocks
+    Cactus { height: i32 },
+}
+
+/// A feature rooted at `origin`, the first cell above the ground it stands on
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct PlacedFeature {
+    pub origin: BlockPos,
+    pub feature: Feature,
+}
+
+impl PlacedFeature {
+    /// Writes the part of the feature that lies inside the chunk at `chunk_pos`. Wood
+    /// replaces air and leaves while everything else only fills air, so overlapping
+    /// features end up the same whatever order they are written in.
+    pub fn write_into(&self, chunk_pos: ChunkPos, chunk: &mut VoxelChunk, blocks: &TerrainBlocks) {
+        let base = (chunk_pos.0 * 16, chunk_pos.1 * 16, chunk_pos.2 * 16);
+        self.for_each_block(blocks, |pos, block| {
+            let local = (pos.0 - base.0, pos.1 - base.1, pos.2 - base.2);
+            if ![local.0, local.1, local.2].iter().all(|coordinate| (0..16).contains(coordinate)) {
+                return;
+            }
+            let (x, y, z) = (local.0 as usize, local.1 as usize, local.2 as usize);
+            let current = chunk.get_block(x, y, z);
+            let replaceable = current == BlockId::AIR || (block == blocks.wood && current == blocks.leaves);
+            if replaceable {
+                chunk.set_block(x, y, z, block);
+            }
+        });
+    }
+    
+    fn for_each_block<F>(&self, blocks: &TerrainBlocks, mut place: F)
+    where
+        F: FnMut(BlockPos, BlockId),
+    {
+        let (ox, oy, oz) = self.origin;
+        match self.feature {
+            Feature::Tree { height } => {
+                for y in 0..height {
+                    place((ox, oy + y, oz), blocks.wood);
+                }
+                // Two wide layers around the top of the trunk, then narrowing to a tip
+                for y in height - 2..=height + 1 {
+                    let radius: i32 = if y < height { 2 } else { 1 };
+                    for dx in -radius..=radius {
+                        for dz in -radius..=radius {
+                            let corner = dx.abs() == radius && dz.abs() == radius;
+                            if corner && (radius == 2 || y == height + 1) {
+                                continue;
+                            }
+                            place((ox + dx, oy + y, oz + dz), blocks.leaves);
+                        }
+                    }
+                }
+            }
+            Feature::Cactus { height } => {
+                for y in 0..height {
+                    place((ox, oy + y, oz), blocks.cactus);
+                }
+            }
+        }
+    }
+}
+
+/// Every feature that reaches into the chunk at `chunk_pos`, found by checking each
+/// column within reach of the chunk. `column_fn` gives the same column values the
+/// terrain phase uses, so features sit on the generated ground.
+pub fn features_for_chunk<F>(chunk_pos: ChunkPos, params: &TerrainParams, column_fn: F) -> Vec<PlacedFeature>
+where
+    F: Fn(f32, f32, &TerrainParams) -> Column,
+{
+    let base = (chunk_pos.0 * 16, chunk_pos.1 * 16, chunk_pos.2 * 16);
+    let mut features = Vec::new();
+    for x in base.0 - MAX_RADIUS..base.0 + 16 + MAX_RADIUS {
+        for z in base.2 - MAX_RADIUS..base.2 + 16 + MAX_RADIUS {
+            let Some(placed) = feature_at(x, z, params, &column_fn) else {
+                continue;
+            };
+            let bottom = placed.origin.1;
+            if bottom < base.1 + 16 && bottom + MAX_HEIGHT >= base.1 {
+                features.push(placed);
+            }
+        }
+    }
+    features
+}
+
+// The feature growing from world column (x, z), if any
+fn feature_at<F>(x: i32, z: i32, params: &TerrainParams, column_fn: &F) -> Option<PlacedFeature>
+where
+    F: Fn(f32, f32, &TerrainParams) -> Column,
+{
+    // The hash decides first so most columns never evaluate the terrain functions
+    let hash = noise::hash2d(params.seed_hash().wrapping_add(PLACEMENT_SEED), x, z);
+    let roll = (hash % 10_000) as f32 / 100.0;
+    if roll >= params.tree_density * MAX_VEGETATION_DENSITY {
+        return None;
+    }
+    let column = column_fn(x as f32, z as f32, params);
+    let biome = column.biome;
+    let definition = biome.definition();
+    if roll >= params.tree_density * definition.vegetation_density {
+        return None;
+    }
+    
+    // Nothing grows on beaches or under water
+    let terrain_height = column.height;
+    let sea_level = params.sea_level;
+    let is_beach = biome.has_beaches() && terrain_height > sea_level - 2.0 && terrain_height < sea_level + 3.0;
+    let surface_y = terrain_height.ceil();
+    if is_beach || surface_y < column.water_level {
+        return None;
+    }
+    
+    let feature = match definition.vegetation {
+        Vegetation::Trees => Feature::Tree { height: 4 + (hash >> 16) as i32 % 3 },
+        Vegetation::Cacti => Feature::Cactus { height: 1 + (hash >> 16) as i32 % 3 },
+    };
+    Some(PlacedFeature {
+        origin: (x, surface_y as i32 + 1, z),
+        feature,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::geometry::blocks::BlockRegistry;
+    use crate::geometry::terrain::TerrainGenerator;
+    use crate::geometry::worldgen::{StandardGenerator, WorldGenerator};
+
+    // A tree rooted in the last x column of its chunk, so its canopy reaches into the
+    // next chunk along x, with the whole tree inside one layer of chunks
+    fn border_tree(params: &TerrainParams) -> PlacedFeature {
+        for cx in 0..64 {
+            for cy in -2..4 {
+                let trees = StandardGenerator.decorations((cx, cy, 0), params);
+                let found = trees.into_iter().find(|placed| {
+                    let Feature::Tree { height } = placed.feature else {
+                        return false;
+                    };
+                    let (x, y, _) = placed.origin;
+                    x.rem_euclid(16) == 15 && y.div_euclid(16) == cy && (y + height + 1).div_euclid(16) == cy
+                });
+                if let Some(placed) = found {
+                    return placed;
+                }
+            }
+        }
+        panic!("no tree crosses a chunk border");
+    }
+
+    fn chunk_blocks(terrain: &mut TerrainGenerator, chunk_pos: ChunkPos) -> Vec<BlockId> {
+        let base = (chunk_pos.0 * 16, chunk_pos.1 * 16, chunk_pos.2 * 16);
+        let mut blocks = Vec::with_capacity(4096);
+        for x in 0..16 {
+            for y in 0..16 {
+                for z in 0..16 {
+                    blocks.push(terrain.get_block_at(base.0 + x, base.1 + y, base.2 + z));
+                }
+            }
+        }
+        blocks
+    }
+
+    #[test]
+    fn trees_crossing_chunk_borders_do_not_depend_on_generation_order() {
+        let params = TerrainParams::default();
+        let tree = border_tree(&params);
+        let Feature::Tree { height } = tree.feature else { unreachable!() };
+        let (x, y, z) = tree.origin;
+        let own = (x.div_euclid(16), y.div_euclid(16), z.div_euclid(16));
+        let next = (own.0 + 1, own.1, own.2);
+
+        let mut forwards = TerrainGenerator::new();
+        forwards.set_terrain_params(params.clone());
+        let own_first = chunk_blocks(&mut forwards, own);
+        let next_second = chunk_blocks(&mut forwards, next);
+
+        let mut backwards = TerrainGenerator::new();
+        backwards.set_terrain_params(params);
+        let next_first = chunk_blocks(&mut backwards, next);
+        let own_second = chunk_blocks(&mut backwards, own);
+
+        assert!(own_first == own_second);
+        assert!(next_first == next_second);
+
+        // The canopy made it into both chunks
+        let blocks = BlockRegistry::default().terrain_blocks();
+        assert_eq!(forwards.get_block_at(x, y + height - 1, z), blocks.wood);
+        assert_eq!(forwards.get_block_at(x + 1, y + height - 1, z), blocks.leaves);
+        assert_eq!(backwards.get_block_at(x + 2, y + height - 1, z), blocks.leaves);
+    }
+}
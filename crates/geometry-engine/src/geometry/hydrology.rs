@@ -0,0 +1,157 @@
+// Rivers and lakes carved into the land surface. Both are functions of the world column
+// alone, so every chunk can still be generated on its own. Rivers follow the zero lines
+// of a domain-warped noise field, which wind across the map as long unbroken channels cut
+// down to sea level. Every cell of a grid holds one hashed lake candidate, kept only
+// where the land already forms a depression: the water level is read off the terrain
+// along the shore and has to stand above the ground in the middle.
+use super::noise;
+use super::terrain::TerrainParams;
+
+// Frequency of the river noise; lower means fewer, longer rivers
+const RIVER_FREQUENCY: f32 = 0.0025;
+// Noise distance from the river line covered by water, and by the sloping valley around it
+const RIVER_WIDTH: f32 = 0.015;
+const VALLEY_WIDTH: f32 = 0.06;
+// Depth of the middle of a river below sea level
+const RIVER_DEPTH: f32 = 3.0;
+
+// Side of the grid cells lake candidates are placed in
+const LAKE_CELL: f32 = 64.0;
+const LAKE_MIN_RADIUS: f32 = 12.0;
+const LAKE_MAX_RADIUS: f32 = 20.0;
+// Depth of the middle of a lake below its water level
+const LAKE_DEPTH: f32 = 5.0;
+// Distance from the centre, in lake radii, at which the bowl has blended into the relief
+const LAKE_BLEND: f32 = 1.5;
+// Unit directions to the points along the shore the water level is taken from, written
+// out so no platform's trigonometry ends up in the terrain
+const SHORE_DIRECTIONS: [(f32, f32); 16] = [
+    (1.0, 0.0),
+    (0.923_879_5, 0.382_683_43),
+    (0.707_106_77, 0.707_106_77),
+    (0.382_683_43, 0.923_879_5),
+    (0.0, 1.0),
+    (-0.382_683_43, 0.923_879_5),
+    (-0.707_106_77, 0.707_106_77),
+    (-0.923_879_5, 0.382_683_43),
+    (-1.0, 0.0),
+    (-0.923_879_5, -0.382_683_43),
+    (-0.707_106_77, -0.707_106_77),
+    (-0.382_683_43, -0.923_879_5),
+    (0.0, -1.0),
+    (0.382_683_43, -0.923_879_5),
+    (0.707_106_77, -0.707_106_77),
+    (0.923_879_5, -0.382_683_43),
+];
+
+// Seed offsets, apart from the terrain and climate noise layers
+const RIVER_SEED: i32 = 10;
+const LAKE_SEED: i32 = 13;
+
+struct Lake {
+    centre: (f32, f32),
+    radius: f32,
+    level: f32,
+}
+
+/// Surface height of a column once rivers and lakes are carved into `relief`, the land
+/// surface function they are cut from. Rivers only ever lower the ground; lakes replace
+/// it with a round bowl that blends back into the relief around the shore.
+pub fn carve<R>(x: f32, z: f32, params: &TerrainParams, relief: R) -> f32
+where
+    R: Fn(f32, f32) -> f32,
+{
+    let mut height = relief(x, z);
+    if let Some(lake) = lake_at(x, z, LAKE_BLEND, params, &relief) {
+        let d = lake.distance(x, z) / lake.radius;
+        let bowl = lake.level - LAKE_DEPTH * (1.0 - d * d);
+        let t = ((d - 1.0) / (LAKE_BLEND - 1.0)).clamp(0.0, 1.0);
+        height = bowl + (height - bowl) * t * t * (3.0 - 2.0 * t);
+    }
+    let mut carved = height;
+
+    let distance = river_distance(x, z, params);
+    if distance < VALLEY_WIDTH {
+        // Rounded channel below sea level, then banks rising back to the land
+        let sea_level = params.sea_level;
+        carved = carved.min(if distance < RIVER_WIDTH {
+            let t = distance / RIVER_WIDTH;
+            sea_level - RIVER_DEPTH * (1.0 - t * t)
+        } else {
+            let t = (distance - RIVER_WIDTH) / (VALLEY_WIDTH - RIVER_WIDTH);
+            sea_level + (height - sea_level) * t * t * (3.0 - 2.0 * t)
+        });
+    }
+    carved
+}
+
+/// Height open cells of a column fill with water up to: the surface of the lake it lies
+/// in, sea level everywhere else
+pub fn water_level<R>(x: f32, z: f32, params: &TerrainParams, relief: R) -> f32
+where
+    R: Fn(f32, f32) -> f32,
+{
+    lake_at(x, z, 1.0, params, &relief).map_or(params.sea_level, |lake| lake.level)
+}
+
+// Distance in noise units from (x, z) to the nearest river line
+fn river_distance(x: f32, z: f32, params: &TerrainParams) -> f32 {
+    let seed = params.seed_hash().wrapping_add(RIVER_SEED);
+    // Warping the input makes the channels meander instead of following the lattice
+    let warp_x = noise::perlin2d(seed.wrapping_add(1), x * 0.01, z * 0.01) * 16.0;
+    let warp_z = noise::perlin2d(seed.wrapping_add(2), x * 0.01, z * 0.01) * 16.0;
+    noise::perlin2d(seed, (x + warp_x) * RIVER_FREQUENCY, (z + warp_z) * RIVER_FREQUENCY).abs()
+}
+
+// The lake reaching within `within` radii of (x, z), if any. The cell's hash picks the
+// candidate's size and position, which keeps the whole bowl inside the cell; only columns
+// that close pay for sampling the shore.
+fn lake_at<R>(x: f32, z: f32, within: f32, params: &TerrainParams, relief: &R) -> Option<Lake>
+where
+    R: Fn(f32, f32) -> f32,
+{
+    let cell = ((x / LAKE_CELL).floor(), (z / LAKE_CELL).floor());
+    let hash = noise::hash2d(params.seed_hash().wrapping_add(LAKE_SEED), cell.0 as i32, cell.1 as i32);
+
+    let fraction = |bits: u32| ((hash >> bits) & 0xff) as f32 / 255.0;
+    let radius = LAKE_MIN_RADIUS + (LAKE_MAX_RADIUS - LAKE_MIN_RADIUS) * fraction(8);
+    let reach = radius * LAKE_BLEND;
+    let room = LAKE_CELL - 2.0 * reach;
+    let centre = (
+        cell.0 * LAKE_CELL + reach + room * fraction(16),
+        cell.1 * LAKE_CELL + reach + room * fraction(24),
+    );
+    let mut lake = Lake { centre, radius, level: 0.0 };
+    if lake.distance(x, z) >= radius * within {
+        return None;
+    }
+
+    // Only a real depression holds a lake: the ground in the middle has to lie below the
+    // lowest point of the shore, on both the water's edge and the outer edge of the blend.
+    // The water fills up to that point, so its top cell is never above the shore's.
+    // Rivers would drain the lake, so a river valley crossing the shore rules it out.
+    let bottom = relief(centre.0, centre.1);
+    let mut shore = f32::MAX;
+    for (dx, dz) in SHORE_DIRECTIONS {
+        for distance in [radius, reach] {
+            let sx = centre.0 + dx * distance;
+            let sz = centre.1 + dz * distance;
+            shore = shore.min(relief(sx, sz));
+            if shore <= bottom || river_distance(sx, sz, params) < VALLEY_WIDTH {
+                return None;
+            }
+        }
+    }
+    lake.level = shore.floor();
+
+    // Basins at sea level are part of the sea
+    (bottom < lake.level && lake.level > params.sea_level + 1.0).then_some(lake)
+}
+
+impl Lake {
+    fn distance(&self, x: f32, z: f32) -> f32 {
+        let dx = x - self.centre.0;
+        let dz = z - self.centre.1;
+        (dx * dx + dz * dz).sqrt()
+    }
+}
@@ -0,0 +1,505 @@
+// Flood-fill lighting. Sky light enters from above and travels straight down without
+// fading; both sky and block light lose one level per block when spreading sideways,
+// and only pass through transparent blocks. Changes are propagated incrementally with
+// a removal queue for light that is no longer supported and an addition queue for
+// light that has to spread, across chunk borders into every loaded chunk.
+use std::collections::{HashMap, VecDeque};
+use super::blocks::{BlockId, BlockRegistry};
+use super::voxel::{Face, VoxelChunk};
+
+type ChunkPos = (i32, i32, i32);
+type BlockPos = (i32, i32, i32);
+
+pub const MAX_LIGHT: u8 = 15;
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum LightChannel {
+    Sky,
+    Block,
+}
+
+impl LightChannel {
+    const ALL: [LightChannel; 2] = [LightChannel::Sky, LightChannel::Block];
+
+    // Sky light lives in the high nibble, block light in the low one
+    fn unpack(&self, packed: u8) -> u8 {
+        match self {
+            LightChannel::Sky => packed >> 4,
+            LightChannel::Block => packed & 0x0f,
+        }
+    }
+
+    fn pack(&self, packed: u8, level: u8) -> u8 {
+        match self {
+            LightChannel::Sky => (packed & 0x0f) | (level << 4),
+            LightChannel::Block => (packed & 0xf0) | level,
+        }
+    }
+}
+
+/// Packed sky and block light of a chunk's cells. Chunks lit evenly (open sky,
+/// solid rock) keep a single value.
+pub enum LightStorage {
+    Uniform(u8),
+    Dense(Box<[u8; 4096]>),
+}
+
+impl Default for LightStorage {
+    fn default() -> Self {
+        LightStorage::Uniform(0)
+    }
+}
+
+impl LightStorage {
+    pub fn uniform(sky: u8, block: u8) -> Self {
+        LightStorage::Uniform(LightChannel::Block.pack(LightChannel::Sky.pack(0, sky), block))
+    }
+
+    fn cell(x: usize, y: usize, z: usize) -> usize {
+        (x * 16 + y) * 16 + z
+    }
+
+    pub fn get(&self, x: usize, y: usize, z: usize, channel: LightChannel) -> u8 {
+        let packed = match self {
+            LightStorage::Uniform(packed) => *packed,
+            LightStorage::Dense(cells) => cells[Self::cell(x, y, z)],
+        };
+        channel.unpack(packed)
+    }
+
+    pub fn set(&mut self, x: usize, y: usize, z: usize, channel: LightChannel, level: u8) {
+        match self {
+            LightStorage::Uniform(packed) => {
+                let updated = channel.pack(*packed, level);
+                if updated != *packed {
+                    let mut cells = Box::new([*packed; 4096]);
+                    cells[Self::cell(x, y, z)] = updated;
+                    *self = LightStorage::Dense(cells);
+                }
+            }
+            LightStorage::Dense(cells) => {
+                let cell = &mut cells[Self::cell(x, y, z)];
+                *cell = channel.pack(*cell, level);
+            }
+        }
+    }
+
+    pub fn heap_size(&self) -> usize {
+        match self {
+            LightStorage::Uniform(_) => 0,
+            LightStorage::Dense(cells) => std::mem::size_of_val(cells.as_ref()),
+        }
+    }
+}
+
+/// Lights a chunk that was just inserted into `chunks`: sky light from above, its own
+/// emissive blocks and the light of loaded neighbours flowing in, then spreads the
+/// result back out. A chunk whose upper neighbour is not loaded is assumed to be open
+/// to the sky, which is corrected once that neighbour arrives.
+pub fn light_new_chunk(chunks: &mut HashMap<ChunkPos, VoxelChunk>, registry: &BlockRegistry, chunk_pos: ChunkPos) {
+    let mut propagation = Propagation::new(chunks, registry);
+    let base = (chunk_pos.0 * 16, chunk_pos.1 * 16, chunk_pos.2 * 16);
+    let above = (chunk_pos.0, chunk_pos.1 + 1, chunk_pos.2);
+    let open_sky = !propagation.chunks.contains_key(&above);
+
+    let chunk = propagation.chunks.get_mut(&chunk_pos).unwrap();
+    let open_air = open_sky && chunk.uniform_block().is_some_and(|block| {
+        let block = registry.get(block);
+        block.transparent && block.light_emission == 0
+    });
+    if open_air {
+        // Empty sky is fully lit throughout; only its border has to pass the light on, to
+        // the cells across it that it would brighten
+        chunk.fill_light(MAX_LIGHT, 0);
+        for face in Face::ALL {
+            let (dx, dy, dz) = face.normal();
+            let Some(neighbour) = propagation.chunks.get(&(chunk_pos.0 + dx, chunk_pos.1 + dy, chunk_pos.2 + dz)) else {
+                continue;
+            };
+            let spread = if face == Face::Bottom { MAX_LIGHT } else { MAX_LIGHT - 1 };
+            for i in 0..16 {
+                for j in 0..16 {
+                    let pos = border_cell(base, face, i, j);
+                    let (x, y, z) = local((pos.0 + dx, pos.1 + dy, pos.2 + dz));
+                    let brightened = neighbour.light_level(x, y, z, LightChannel::Sky) < spread
+                        && registry.get(neighbour.get_block(x, y, z)).transparent;
+                    if brightened {
+                        propagation.additions.push_back((pos, LightChannel::Sky));
+                    }
+                }
+            }
+        }
+    } else {
+        chunk.fill_light(0, 0);
+        let mut sources = Vec::new();
+        for x in 0..16 {
+            for y in 0..16 {
+                for z in 0..16 {
+                    let block = registry.get(chunk.get_block(x, y, z));
+                    let pos = (base.0 + x as i32, base.1 + y as i32, base.2 + z as i32);
+                    if block.light_emission > 0 {
+                        sources.push((pos, LightChannel::Block, block.light_emission.min(MAX_LIGHT)));
+                    }
+                    if open_sky && y == 15 && block.transparent {
+                        sources.push((pos, LightChannel::Sky, MAX_LIGHT));
+                    }
+                }
+            }
+        }
+        for (pos, channel, level) in sources {
+            propagation.set_light(pos, channel, level);
+            propagation.additions.push_back((pos, channel));
+        }
+    }
+
+    // Light already present next to the chunk flows into it. Sky light can't brighten
+    // open air that is fully lit already.
+    let channels: &[LightChannel] = if open_air { &[LightChannel::Block] } else { &LightChannel::ALL };
+    for face in Face::ALL {
+        let (dx, dy, dz) = face.normal();
+        let Some(neighbour) = propagation.chunks.get(&(chunk_pos.0 + dx, chunk_pos.1 + dy, chunk_pos.2 + dz)) else {
+            continue;
+        };
+        for i in 0..16 {
+            for j in 0..16 {
+                let pos = border_cell(base, face, i, j);
+                let outside = (pos.0 + dx, pos.1 + dy, pos.2 + dz);
+                let (x, y, z) = local(outside);
+                for &channel in channels {
+                    if neighbour.light_level(x, y, z, channel) > 0 {
+                        propagation.additions.push_back((outside, channel));
+                    }
+                }
+            }
+        }
+    }
+    propagation.run();
+
+    // The chunk below may have assumed open sky before this one was loaded
+    let below_base = (base.0, base.1 - 16, base.2);
+    for x in 0..16 {
+        for z in 0..16 {
+            let top_below = (below_base.0 + x, below_base.1 + 15, below_base.2 + z);
+            let bottom = (base.0 + x, base.1, base.2 + z);
+            if propagation.light(top_below, LightChannel::Sky) == Some(MAX_LIGHT)
+                && propagation.light(bottom, LightChannel::Sky) != Some(MAX_LIGHT)
+            {
+                propagation.set_light(top_below, LightChannel::Sky, 0);
+                propagation.removals.push_back((top_below, LightChannel::Sky, MAX_LIGHT));
+            }
+        }
+    }
+    propagation.run();
+}
+
+/// Updates light around a block that was just replaced
+pub fn relight_block(chunks: &mut HashMap<ChunkPos, VoxelChunk>, registry: &BlockRegistry, pos: BlockPos) {
+    let mut propagation = Propagation::new(chunks, registry);
+    let Some(block) = propagation.block(pos) else {
+        return;
+    };
+    let block = registry.get(block);
+
+    // Take away whatever light the cell held or passed on, then let it flow back in
+    for channel in LightChannel::ALL {
+        let old = propagation.light(pos, channel).unwrap_or(0);
+        if old > 0 {
+            propagation.set_light(pos, channel, 0);
+            propagation.removals.push_back((pos, channel, old));
+        }
+        if block.transparent {
+            for face in Face::ALL {
+                let (dx, dy, dz) = face.normal();
+                propagation.additions.push_back(((pos.0 + dx, pos.1 + dy, pos.2 + dz), channel));
+            }
+        }
+    }
+    if block.light_emission > 0 {
+        propagation.set_light(pos, LightChannel::Block, block.light_emission.min(MAX_LIGHT));
+        propagation.additions.push_back((pos, LightChannel::Block));
+    }
+    propagation.run();
+}
+
+// Cell (i, j) of the chunk layer facing `face`, in world coordinates
+fn border_cell(base: BlockPos, face: Face, i: i32, j: i32) -> BlockPos {
+    let d = face.axis();
+    let mut local = [0; 3];
+    let (dx, dy, dz) = face.normal();
+    local[d] = if dx + dy + dz > 0 { 15 } else { 0 };
+    local[(d + 1) % 3] = i;
+    local[(d + 2) % 3] = j;
+    (base.0 + local[0], base.1 + local[1], base.2 + local[2])
+}
+
+// Position of a block within its chunk
+fn local(pos: BlockPos) -> (usize, usize, usize) {
+    (pos.0.rem_euclid(16) as usize, pos.1.rem_euclid(16) as usize, pos.2.rem_euclid(16) as usize)
+}
+
+// Breadth-first light queues over the loaded chunks
+struct Propagation<'a> {
+    chunks: &'a mut HashMap<ChunkPos, VoxelChunk>,
+    registry: &'a BlockRegistry,
+    additions: VecDeque<(BlockPos, LightChannel)>,
+    // Cells that were darkened, with the level they had before
+    removals: VecDeque<(BlockPos, LightChannel, u8)>,
+    // Chunks whose light changed, each with a bit per neighbouring chunk (by offset, the
+    // chunk itself included) whose mesh samples a changed cell. Consecutive writes to one
+    // chunk share an entry; all of them are flagged once the run is over.
+    touched: Vec<(ChunkPos, u32)>,
+}
+
+impl<'a> Propagation<'a> {
+    fn new(chunks: &'a mut HashMap<ChunkPos, VoxelChunk>, registry: &'a BlockRegistry) -> Self {
+        Self {
+            chunks,
+            registry,
+            additions: VecDeque::new(),
+            removals: VecDeque::new(),
+            touched: Vec::new(),
+        }
+    }
+
+    fn locate(pos: BlockPos) -> (ChunkPos, usize, usize, usize) {
+        (
+            (pos.0.div_euclid(16), pos.1.div_euclid(16), pos.2.div_euclid(16)),
+            pos.0.rem_euclid(16) as usize,
+            pos.1.rem_euclid(16) as usize,
+            pos.2.rem_euclid(16) as usize,
+        )
+    }
+
+    fn block(&self, pos: BlockPos) -> Option<BlockId> {
+        let (chunk_pos, x, y, z) = Self::locate(pos);
+        self.chunks.get(&chunk_pos).map(|chunk| chunk.get_block(x, y, z))
+    }
+
+    fn light(&self, pos: BlockPos, channel: LightChannel) -> Option<u8> {
+        let (chunk_pos, x, y, z) = Self::locate(pos);
+        self.chunks.get(&chunk_pos).map(|chunk| chunk.light_level(x, y, z, channel))
+    }
+
+    fn set_light(&mut self, pos: BlockPos, channel: LightChannel, level: u8) {
+        let (chunk_pos, x, y, z) = Self::locate(pos);
+        let Some(chunk) = self.chunks.get_mut(&chunk_pos) else {
+            return;
+        };
+        chunk.set_light_level(x, y, z, channel, level);
+
+        // Neighbouring meshes sample the cells along the border
+        let span = |local: usize| match local {
+            0 => -1..=0,
+            15 => 0..=1,
+            _ => 0..=0,
+        };
+        let mut neighbours = 0;
+        for dx in span(x) {
+            for dy in span(y) {
+                for dz in span(z) {
+                    neighbours |= 1 << ((dx + 1) * 9 + (dy + 1) * 3 + dz + 1);
+                }
+            }
+        }
+        match self.touched.last_mut() {
+            Some((last, bits)) if *last == chunk_pos => *bits |= neighbours,
+            _ => self.touched.push((chunk_pos, neighbours)),
+        }
+    }
+
+    // Flags the meshes of every chunk that shows light changed since the last call
+    fn mark_touched_dirty(&mut self) {
+        let mut touched: HashMap<ChunkPos, u32> = HashMap::new();
+        for (chunk_pos, bits) in self.touched.drain(..) {
+            *touched.entry(chunk_pos).or_default() |= bits;
+        }
+        for (chunk_pos, bits) in touched {
+            for slot in 0..27 {
+                if bits & (1 << slot) == 0 {
+                    continue;
+                }
+                let neighbour = (chunk_pos.0 + slot / 9 - 1, chunk_pos.1 + slot / 3 % 3 - 1, chunk_pos.2 + slot % 3 - 1);
+                if let Some(chunk) = self.chunks.get_mut(&neighbour) {
+                    chunk.mark_dirty();
+                }
+            }
+        }
+    }
+
+    fn is_transparent(&self, pos: BlockPos) -> bool {
+        self.block(pos).is_some_and(|block| self.registry.get(block).transparent)
+    }
+
+    fn run(&mut self) {
+        while let Some((pos, channel, old)) = self.removals.pop_front() {
+            for face in Face::ALL {
+                let (dx, dy, dz) = face.normal();
+                let next = (pos.0 + dx, pos.1 + dy, pos.2 + dz);
+                let Some(level) = self.light(next, channel) else {
+                    continue;
+                };
+                if level == 0 {
+                    continue;
+                }
+                // Dimmer neighbours, and sky light falling straight down, were lit through this cell
+                let lit_from_here = level < old
+                    || (channel == LightChannel::Sky && face == Face::Bottom && old == MAX_LIGHT);
+                if lit_from_here {
+                    self.set_light(next, channel, 0);
+                    self.removals.push_back((next, channel, level));
+
+                    // Emissive blocks keep shining on their own
+                    let emission = self.block(next).map_or(0, |block| self.registry.get(block).light_emission);
+                    if channel == LightChannel::Block && emission > 0 {
+                        self.set_light(next, channel, emission.min(MAX_LIGHT));
+                        self.additions.push_back((next, channel));
+                    }
+                } else {
+                    self.additions.push_back((next, channel));
+                }
+            }
+        }
+
+        while let Some((pos, channel)) = self.additions.pop_front() {
+            let Some(level) = self.light(pos, channel) else {
+                continue;
+            };
+            if level == 0 {
+                continue;
+            }
+            for face in Face::ALL {
+                let (dx, dy, dz) = face.normal();
+                let next = (pos.0 + dx, pos.1 + dy, pos.2 + dz);
+                if !self.is_transparent(next) {
+                    continue;
+                }
+                let spread = if channel == LightChannel::Sky && face == Face::Bottom && level == MAX_LIGHT {
+                    MAX_LIGHT
+                } else {
+                    level - 1
+                };
+                if self.light(next, channel).is_some_and(|current| spread > current) {
+                    self.set_light(next, channel, spread);
+                    self.additions.push_back((next, channel));
+                }
+            }
+        }
+        self.mark_touched_dirty();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::geometry::terrain::TerrainParams;
+    use crate::geometry::voxel::ChunkColumns;
+    use crate::geometry::worldgen::StandardGenerator;
+
+    // Chunks around the origin in the order they are loaded and lit
+    fn chunk_positions() -> Vec<ChunkPos> {
+        let mut positions = Vec::new();
+        for y in (-2..=2).rev() {
+            for x in -1..=1 {
+                for z in -1..=1 {
+                    positions.push((x, y, z));
+                }
+            }
+        }
+        positions
+    }
+
+    // Generates and lights every chunk, with `edits` written into the blocks beforehand
+    fn load(registry: &BlockRegistry, edits: &[(BlockPos, BlockId)]) -> HashMap<ChunkPos, VoxelChunk> {
+        let params = TerrainParams::default();
+        let mut chunks = HashMap::new();
+        for chunk_pos in chunk_positions() {
+            let columns = ChunkColumns::sample(chunk_pos.0, chunk_pos.2, &params, &StandardGenerator);
+            let mut chunk = VoxelChunk::new_with_terrain_params(
+                chunk_pos.0,
+                chunk_pos.1,
+                chunk_pos.2,
+                &columns,
+                &params,
+                registry.terrain_blocks(),
+                &StandardGenerator,
+            );
+            for &(pos, block) in edits {
+                let (chunk_of, x, y, z) = Propagation::locate(pos);
+                if chunk_of == chunk_pos {
+                    chunk.set_block(x, y, z, block);
+                }
+            }
+            chunks.insert(chunk_pos, chunk);
+            light_new_chunk(&mut chunks, registry, chunk_pos);
+        }
+        chunks
+    }
+
+    fn light_of(chunks: &HashMap<ChunkPos, VoxelChunk>) -> Vec<(ChunkPos, LightChannel, Vec<u8>)> {
+        let mut light = Vec::new();
+        for chunk_pos in chunk_positions() {
+            let chunk = &chunks[&chunk_pos];
+            for channel in LightChannel::ALL {
+                let mut levels = Vec::with_capacity(4096);
+                for x in 0..16 {
+                    for y in 0..16 {
+                        for z in 0..16 {
+                            levels.push(chunk.light_level(x, y, z, channel));
+                        }
+                    }
+                }
+                light.push((chunk_pos, channel, levels));
+            }
+        }
+        light
+    }
+
+    // Highest non-air block of a column
+    fn surface(chunks: &HashMap<ChunkPos, VoxelChunk>, x: i32, z: i32) -> i32 {
+        let block_at = |pos: BlockPos| {
+            let (chunk_pos, lx, ly, lz) = Propagation::locate(pos);
+            chunks[&chunk_pos].get_block(lx, ly, lz)
+        };
+        (-32..48).rev().find(|&y| block_at((x, y, z)) != BlockId::AIR).expect("column has ground")
+    }
+
+    #[test]
+    fn incremental_relighting_matches_a_fresh_load() {
+        let registry = BlockRegistry::default();
+        let blocks = registry.terrain_blocks();
+        let glowstone = registry.id("glowstone").unwrap();
+        let mut chunks = load(&registry, &[]);
+
+        let mut edits = Vec::new();
+        // A shaft dug into the ground, lit from its bottom
+        let top = surface(&chunks, 5, 5);
+        for y in top - 8..=top {
+            edits.push(((5, y, 5), BlockId::AIR));
+        }
+        edits.push(((5, top - 8, 5), glowstone));
+        // A trench across the chunk border at x = 0, then roofed over
+        let top = surface(&chunks, 0, -6);
+        for x in -3..3 {
+            for y in top - 4..=top {
+                edits.push(((x, y, -6), BlockId::AIR));
+            }
+            edits.push(((x, top + 1, -6), blocks.stone));
+        }
+        // Glowstone on both sides of the border at z = 16, one of them dug out again
+        let top = surface(&chunks, 8, 15);
+        edits.push(((8, top + 1, 15), glowstone));
+        edits.push(((8, top + 1, 16), glowstone));
+        edits.push(((8, top + 1, 16), BlockId::AIR));
+
+        for &(pos, block) in &edits {
+            let (chunk_pos, x, y, z) = Propagation::locate(pos);
+            chunks.get_mut(&chunk_pos).unwrap().set_block(x, y, z, block);
+            relight_block(&mut chunks, &registry, pos);
+        }
+
+        let fresh = load(&registry, &edits);
+        for ((chunk_pos, channel, incremental), (_, _, expected)) in light_of(&chunks).into_iter().zip(light_of(&fresh)) {
+            assert!(incremental == expected, "{:?} light differs in chunk {:?}", channel, chunk_pos);
+        }
+    }
+}
@@ -1,8 +1,21 @@
+pub mod blocks;
+pub mod climate;
+pub mod edits;
+pub mod features;
+pub mod hydrology;
+pub mod light;
+pub mod noise;
+pub mod region;
 pub mod terrain;
 pub mod voxel;
+pub mod worldgen;
 
-use crate::GeometryData;
-use terrain::{TerrainGenerator, TerrainParams};
+use crate::{ChunkMeshUpdate, GeometryData};
+use region::RegionError;
+use terrain::{CacheLimits, GenerationBudget, RaycastHit, TerrainGenerator, TerrainParams, ViewFrustum};
+use blocks::{BlockId, BlockRegistry};
+use voxel::{MeshingMode, TextureAtlas};
+use worldgen::WorldGenerator;
 
 pub struct AnimationState {
     terrain: TerrainGenerator,
@@ -19,16 +32,117 @@ impl AnimationState {
         self.terrain.update_camera(camera_x, camera_y, camera_z, radius);
     }
     
+    pub fn set_view_frustum(&mut self, frustum: Option<ViewFrustum>) {
+        self.terrain.set_view_frustum(frustum);
+    }
+    
     pub fn set_render_distance(&mut self, distance: i32) {
         self.terrain.set_render_distance(distance);
     }
     
+    pub fn set_world_height_range(&mut self, min_height: i32, max_height: i32) {
+        self.terrain.set_world_height_range(min_height, max_height);
+    }
+    
     pub fn set_terrain_params(&mut self, params: TerrainParams) {
         self.terrain.set_terrain_params(params);
     }
+    
+    pub fn get_block_at(&mut self, x: i32, y: i32, z: i32) -> BlockId {
+        self.terrain.get_block_at(x, y, z)
+    }
+    
+    pub fn set_block_at(&mut self, x: i32, y: i32, z: i32, block: BlockId) {
+        self.terrain.set_block_at(x, y, z, block);
+    }
+    
+    pub fn raycast(&self, origin: [f32; 3], direction: [f32; 3], max_distance: f32, skip_non_solid: bool) -> Option<RaycastHit> {
+        self.terrain.raycast(origin, direction, max_distance, skip_non_solid)
+    }
+    
+    pub fn edit_count(&self) -> usize {
+        self.terrain.edit_count()
+    }
+    
+    pub fn clear_edits(&mut self) {
+        self.terrain.clear_edits();
+    }
+    
+    pub fn save_regions(&self) -> Vec<((i32, i32, i32), Vec<u8>)> {
+        self.terrain.save_regions()
+    }
+    
+    pub fn load_region(&mut self, bytes: &[u8]) -> Result<(), RegionError> {
+        self.terrain.load_region(bytes)
+    }
+    
+    #[cfg(not(target_arch = "wasm32"))]
+    pub fn save_to_dir(&self, dir: &std::path::Path) -> std::io::Result<()> {
+        self.terrain.save_to_dir(dir)
+    }
+    
+    #[cfg(not(target_arch = "wasm32"))]
+    pub fn load_from_dir(&mut self, dir: &std::path::Path) -> std::io::Result<()> {
+        self.terrain.load_from_dir(dir)
+    }
+    
+    pub fn set_block_registry(&mut self, registry: BlockRegistry) {
+        self.terrain.set_block_registry(registry);
+    }
+    
+    pub fn block_id(&self, name: &str) -> Option<BlockId> {
+        self.terrain.block_id(name)
+    }
+    
+    pub fn set_world_generator(&mut self, name: &str) -> bool {
+        self.terrain.set_world_generator(name)
+    }
+    
+    pub fn register_world_generator(&mut self, name: &str, generator: Box<dyn WorldGenerator>) {
+        self.terrain.register_world_generator(name, generator);
+    }
+    
+    pub fn world_generator(&self) -> &str {
+        self.terrain.world_generator()
+    }
+    
+    pub fn world_generator_names(&self) -> Vec<String> {
+        self.terrain.world_generator_names()
+    }
+    
+    pub fn set_meshing_mode(&mut self, mode: MeshingMode) {
+        self.terrain.set_meshing_mode(mode);
+    }
+    
+    pub fn set_texture_atlas(&mut self, atlas: Option<TextureAtlas>) {
+        self.terrain.set_texture_atlas(atlas);
+    }
+
+    pub fn set_cache_limits(&mut self, limits: CacheLimits) {
+        self.terrain.set_cache_limits(limits);
+    }
+    
+    pub fn set_generation_budget(&mut self, budget: GenerationBudget) {
+        self.terrain.set_generation_budget(budget);
+    }
+    
+    pub fn pending_chunk_count(&self) -> usize {
+        self.terrain.pending_chunk_count()
+    }
+    
+    pub fn cached_chunk_count(&self) -> usize {
+        self.terrain.cached_chunk_count()
+    }
+    
+    pub fn cache_size_bytes(&self) -> usize {
+        self.terrain.cache_size_bytes()
+    }
 
     pub fn generate_geometry(&mut self) -> GeometryData {
-        let (vertices, indices, normals, colors) = self.terrain.generate();
-        GeometryData::new(vertices, indices, normals, colors)
+        GeometryData::from(self.terrain.generate())
+    }
+    
+    pub fn generate_chunk_meshes(&mut self) -> ChunkMeshUpdate {
+        ChunkMeshUpdate::from(self.terrain.generate_chunk_meshes())
     }
 }
\ No newline at end of file
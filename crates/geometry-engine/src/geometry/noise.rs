@@ -0,0 +1,182 @@
+// Seeded gradient (Perlin) noise and fractal Brownian motion.
+// Everything is computed from integer lattice hashes and plain f32 arithmetic,
+// so the same seed yields bit-identical values on every platform.
+
+/// Fractal Brownian motion settings: how many octaves of noise are summed,
+/// how fast their frequency grows (lacunarity) and their amplitude decays (gain)
+#[derive(Clone, Copy, Debug)]
+pub struct Fbm {
+    pub octaves: u32,
+    pub lacunarity: f32,
+    pub gain: f32,
+}
+
+impl Fbm {
+    /// Sum of 2D noise octaves, normalized back to roughly [-1, 1]
+    pub fn sample2d(&self, seed: i32, x: f32, y: f32) -> f32 {
+        self.accumulate(seed, |octave_seed, frequency| {
+            perlin2d(octave_seed, x * frequency, y * frequency)
+        })
+    }
+
+    /// Sum of 3D noise octaves, normalized back to roughly [-1, 1]
+    pub fn sample3d(&self, seed: i32, x: f32, y: f32, z: f32) -> f32 {
+        self.accumulate(seed, |octave_seed, frequency| {
+            perlin3d(octave_seed, x * frequency, y * frequency, z * frequency)
+        })
+    }
+
+    fn accumulate<F>(&self, seed: i32, sample: F) -> f32
+    where
+        F: Fn(i32, f32) -> f32,
+    {
+        let mut total = 0.0;
+        let mut amplitude = 1.0;
+        let mut frequency = 1.0;
+        let mut max_amplitude = 0.0;
+
+        for octave in 0..self.octaves.max(1) {
+            // Give every octave its own lattice so they don't line up at the origin
+            let octave_seed = seed.wrapping_add((octave as i32).wrapping_mul(1013));
+            total += sample(octave_seed, frequency) * amplitude;
+            max_amplitude += amplitude;
+            amplitude *= self.gain;
+            frequency *= self.lacunarity;
+        }
+
+        total / max_amplitude
+    }
+}
+
+/// 2D gradient noise in roughly [-1, 1]
+pub fn perlin2d(seed: i32, x: f32, y: f32) -> f32 {
+    let x0 = x.floor();
+    let y0 = y.floor();
+    let ix = x0 as i32;
+    let iy = y0 as i32;
+    let fx = x - x0;
+    let fy = y - y0;
+
+    let a = grad2d(hash2d(seed, ix, iy), fx, fy);
+    let b = grad2d(hash2d(seed, ix.wrapping_add(1), iy), fx - 1.0, fy);
+    let c = grad2d(hash2d(seed, ix, iy.wrapping_add(1)), fx, fy - 1.0);
+    let d = grad2d(hash2d(seed, ix.wrapping_add(1), iy.wrapping_add(1)), fx - 1.0, fy - 1.0);
+
+    let ux = fade(fx);
+    let uy = fade(fy);
+
+    let x1 = lerp(a, b, ux);
+    let x2 = lerp(c, d, ux);
+
+    // Diagonal gradients peak at sqrt(2) / 2, scale up to use the full range
+    lerp(x1, x2, uy) * std::f32::consts::SQRT_2
+}
+
+/// 3D gradient noise in roughly [-1, 1]
+pub fn perlin3d(seed: i32, x: f32, y: f32, z: f32) -> f32 {
+    let x0 = x.floor();
+    let y0 = y.floor();
+    let z0 = z.floor();
+    let ix = x0 as i32;
+    let iy = y0 as i32;
+    let iz = z0 as i32;
+    let fx = x - x0;
+    let fy = y - y0;
+    let fz = z - z0;
+    let jx = ix.wrapping_add(1);
+    let jy = iy.wrapping_add(1);
+    let jz = iz.wrapping_add(1);
+
+    // 8 corner points of the cube
+    let a = grad3d(hash3d(seed, ix, iy, iz), fx, fy, fz);
+    let b = grad3d(hash3d(seed, jx, iy, iz), fx - 1.0, fy, fz);
+    let c = grad3d(hash3d(seed, ix, jy, iz), fx, fy - 1.0, fz);
+    let d = grad3d(hash3d(seed, jx, jy, iz), fx - 1.0, fy - 1.0, fz);
+    let e = grad3d(hash3d(seed, ix, iy, jz), fx, fy, fz - 1.0);
+    let f = grad3d(hash3d(seed, jx, iy, jz), fx - 1.0, fy, fz - 1.0);
+    let g = grad3d(hash3d(seed, ix, jy, jz), fx, fy - 1.0, fz - 1.0);
+    let h = grad3d(hash3d(seed, jx, jy, jz), fx - 1.0, fy - 1.0, fz - 1.0);
+
+    let ux = fade(fx);
+    let uy = fade(fy);
+    let uz = fade(fz);
+
+    // Interpolate along x axis
+    let x1 = lerp(a, b, ux);
+    let x2 = lerp(c, d, ux);
+    let x3 = lerp(e, f, ux);
+    let x4 = lerp(g, h, ux);
+
+    // Interpolate along y axis
+    let y1 = lerp(x1, x2, uy);
+    let y2 = lerp(x3, x4, uy);
+
+    // Interpolate along z axis
+    lerp(y1, y2, uz)
+}
+
+// Quintic smoothstep, continuous in the second derivative
+fn fade(t: f32) -> f32 {
+    t * t * t * (t * (t * 6.0 - 15.0) + 10.0)
+}
+
+fn lerp(a: f32, b: f32, t: f32) -> f32 {
+    a + (b - a) * t
+}
+
+// Dot product with one of 8 unit-length gradient directions
+fn grad2d(hash: u32, x: f32, y: f32) -> f32 {
+    const DIAGONAL: f32 = std::f32::consts::FRAC_1_SQRT_2;
+    match hash & 7 {
+        0 => x,
+        1 => -x,
+        2 => y,
+        3 => -y,
+        4 => (x + y) * DIAGONAL,
+        5 => (x - y) * DIAGONAL,
+        6 => (-x + y) * DIAGONAL,
+        _ => (-x - y) * DIAGONAL,
+    }
+}
+
+// Dot product with one of the 12 cube edge directions (improved Perlin noise)
+fn grad3d(hash: u32, x: f32, y: f32, z: f32) -> f32 {
+    match hash % 12 {
+        0 => x + y,
+        1 => -x + y,
+        2 => x - y,
+        3 => -x - y,
+        4 => x + z,
+        5 => -x + z,
+        6 => x - z,
+        7 => -x - z,
+        8 => y + z,
+        9 => -y + z,
+        10 => y - z,
+        _ => -y - z,
+    }
+}
+
+/// Well mixed hash of a grid point, also used for per-column placement decisions
+pub fn hash2d(seed: i32, x: i32, y: i32) -> u32 {
+    mix(seed as u32 ^ (x as u32).wrapping_mul(0x27d4_eb2d) ^ (y as u32).wrapping_mul(0x1656_67b1))
+}
+
+fn hash3d(seed: i32, x: i32, y: i32, z: i32) -> u32 {
+    mix(
+        seed as u32
+            ^ (x as u32).wrapping_mul(0x27d4_eb2d)
+            ^ (y as u32).wrapping_mul(0x1656_67b1)
+            ^ (z as u32).wrapping_mul(0x9e37_79b1),
+    )
+}
+
+// Integer avalanche so neighbouring lattice points get unrelated gradients
+fn mix(mut h: u32) -> u32 {
+    h ^= h >> 16;
+    h = h.wrapping_mul(0x7feb_352d);
+    h ^= h >> 15;
+    h = h.wrapping_mul(0x846c_a68b);
+    h ^= h >> 16;
+    h
+}
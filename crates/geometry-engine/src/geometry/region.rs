@@ -0,0 +1,347 @@
+// Binary region files for saving worlds, modelled on Minecraft's Anvil format.
+//
+// A region covers 8x8x8 chunks. All integers are little-endian:
+//
+//   magic        b"VXRG"
+//   version      u16
+//   region pos   3 x i32
+//   params       u32 length + `TerrainParams` as JSON, seed included
+//   generator    u32 length + UTF-8 name of the world generator
+//   edits        u32 count + count x (world pos 3 x i32, block id u16), the player edits
+//                inside the region, all in stored chunks
+//   index        512 x (offset u32, length u32), offset from the start of the file,
+//                length 0 for chunks that are not stored
+//   chunks       compression u8 (0 = none, 1 = zlib) + 16x16x16 u16 block ids in x, y, z order
+//
+// The index lets a single chunk be decoded without touching the rest of the file. Stored
+// chunks hold the ground as it was saved, so terrain changes in later versions leave it
+// alone. Edits are listed on their own because a chunk's blocks can't tell an edit from the
+// terrain it matches.
+use std::fmt;
+use super::terrain::TerrainParams;
+use super::blocks::BlockId;
+use super::voxel::VoxelChunk;
+
+type ChunkPos = (i32, i32, i32);
+type RegionPos = (i32, i32, i32);
+type BlockPos = (i32, i32, i32);
+
+/// Block ids of a decoded chunk, indexed `[x][y][z]`
+pub type ChunkBlocks = Box<[[[BlockId; 16]; 16]; 16]>;
+
+/// Chunks per region along each axis
+pub const REGION_SIZE: i32 = 8;
+
+const MAGIC: &[u8; 4] = b"VXRG";
+const VERSION: u16 = 1;
+const INDEX_ENTRIES: usize = (REGION_SIZE * REGION_SIZE * REGION_SIZE) as usize;
+const CHUNK_BYTES: usize = 16 * 16 * 16 * 2;
+const EDIT_BYTES: usize = 3 * 4 + 2;
+
+const COMPRESSION_NONE: u8 = 0;
+const COMPRESSION_ZLIB: u8 = 1;
+
+#[derive(Debug)]
+pub enum RegionError {
+    InvalidMagic,
+    UnsupportedVersion(u16),
+    Truncated,
+    InvalidParams(String),
+    InvalidGenerator,
+    /// The region names a world generator that is not registered here
+    UnknownGenerator(String),
+    InvalidChunk(ChunkPos),
+    /// An edit lies outside the chunks stored in its region
+    InvalidEdit(BlockPos),
+}
+
+impl fmt::Display for RegionError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            RegionError::InvalidMagic => write!(f, "not a region file"),
+            RegionError::UnsupportedVersion(version) => write!(f, "unsupported region version {}", version),
+            RegionError::Truncated => write!(f, "region file is truncated"),
+            RegionError::InvalidParams(message) => write!(f, "invalid terrain parameters: {}", message),
+            RegionError::InvalidGenerator => write!(f, "invalid world generator name"),
+            RegionError::UnknownGenerator(name) => write!(f, "unknown world generator `{}`", name),
+            RegionError::InvalidChunk(pos) => write!(f, "corrupt chunk data at {:?}", pos),
+            RegionError::InvalidEdit(pos) => write!(f, "edit at {:?} lies outside the stored chunks", pos),
+        }
+    }
+}
+
+impl std::error::Error for RegionError {}
+
+/// Region containing a chunk
+pub fn region_of(chunk_pos: ChunkPos) -> RegionPos {
+    (
+        chunk_pos.0.div_euclid(REGION_SIZE),
+        chunk_pos.1.div_euclid(REGION_SIZE),
+        chunk_pos.2.div_euclid(REGION_SIZE),
+    )
+}
+
+/// File name a region is stored under on disk
+pub fn region_file_name(region_pos: RegionPos) -> String {
+    format!("r.{}.{}.{}.vxr", region_pos.0, region_pos.1, region_pos.2)
+}
+
+/// Chunk containing a world position
+pub fn chunk_of(world_pos: BlockPos) -> ChunkPos {
+    (world_pos.0.div_euclid(16), world_pos.1.div_euclid(16), world_pos.2.div_euclid(16))
+}
+
+// Slot of a chunk in its region's index
+fn index_slot(chunk_pos: ChunkPos) -> usize {
+    let x = chunk_pos.0.rem_euclid(REGION_SIZE);
+    let y = chunk_pos.1.rem_euclid(REGION_SIZE);
+    let z = chunk_pos.2.rem_euclid(REGION_SIZE);
+    ((x * REGION_SIZE + y) * REGION_SIZE + z) as usize
+}
+
+/// Builds one region file from chunks added in any order
+pub struct RegionWriter {
+    region_pos: RegionPos,
+    params: TerrainParams,
+    generator: String,
+    edits: Vec<(BlockPos, BlockId)>,
+    // Compressed payload of every stored chunk, by index slot
+    chunks: Vec<Option<Vec<u8>>>,
+}
+
+impl RegionWriter {
+    pub fn new(region_pos: RegionPos, params: &TerrainParams, generator: &str) -> Self {
+        Self {
+            region_pos,
+            params: params.clone(),
+            generator: generator.to_string(),
+            edits: Vec::new(),
+            chunks: vec![None; INDEX_ENTRIES],
+        }
+    }
+
+    /// Stores the blocks of a chunk, which must lie inside this region
+    pub fn add_chunk(&mut self, chunk_pos: ChunkPos, chunk: &VoxelChunk) {
+        debug_assert_eq!(region_of(chunk_pos), self.region_pos);
+
+        let mut raw = Vec::with_capacity(CHUNK_BYTES);
+        for x in 0..16 {
+            for y in 0..16 {
+                for z in 0..16 {
+                    raw.extend_from_slice(&chunk.get_block(x, y, z).0.to_le_bytes());
+                }
+            }
+        }
+
+        let mut payload = vec![COMPRESSION_ZLIB];
+        payload.extend(miniz_oxide::deflate::compress_to_vec_zlib(&raw, 6));
+        self.chunks[index_slot(chunk_pos)] = Some(payload);
+    }
+
+    /// Stores one player edit, which must lie in a chunk added to this region
+    pub fn add_edit(&mut self, world_pos: BlockPos, block: BlockId) {
+        debug_assert_eq!(region_of(chunk_of(world_pos)), self.region_pos);
+        self.edits.push((world_pos, block));
+    }
+
+    pub fn finish(self) -> Vec<u8> {
+        let params = serde_json::to_vec(&self.params).expect("terrain parameters serialize to JSON");
+
+        let mut bytes = Vec::new();
+        bytes.extend_from_slice(MAGIC);
+        bytes.extend_from_slice(&VERSION.to_le_bytes());
+        for coordinate in [self.region_pos.0, self.region_pos.1, self.region_pos.2] {
+            bytes.extend_from_slice(&coordinate.to_le_bytes());
+        }
+        bytes.extend_from_slice(&(params.len() as u32).to_le_bytes());
+        bytes.extend_from_slice(&params);
+        bytes.extend_from_slice(&(self.generator.len() as u32).to_le_bytes());
+        bytes.extend_from_slice(self.generator.as_bytes());
+        bytes.extend_from_slice(&(self.edits.len() as u32).to_le_bytes());
+        for ((x, y, z), block) in &self.edits {
+            for coordinate in [x, y, z] {
+                bytes.extend_from_slice(&coordinate.to_le_bytes());
+            }
+            bytes.extend_from_slice(&block.0.to_le_bytes());
+        }
+
+        // Chunk data starts right after the index
+        let mut offset = bytes.len() + INDEX_ENTRIES * 8;
+        for chunk in &self.chunks {
+            let length = chunk.as_ref().map_or(0, Vec::len);
+            let entry_offset = if length > 0 { offset } else { 0 };
+            bytes.extend_from_slice(&(entry_offset as u32).to_le_bytes());
+            bytes.extend_from_slice(&(length as u32).to_le_bytes());
+            offset += length;
+        }
+        for chunk in self.chunks.into_iter().flatten() {
+            bytes.extend(chunk);
+        }
+
+        bytes
+    }
+}
+
+/// Parsed region header over the raw file bytes; chunks are decoded on demand
+pub struct RegionReader<'a> {
+    bytes: &'a [u8],
+    region_pos: RegionPos,
+    params: TerrainParams,
+    generator: String,
+    edits: Vec<(BlockPos, BlockId)>,
+    // (offset, length) of every index slot
+    index: Vec<(usize, usize)>,
+}
+
+impl<'a> RegionReader<'a> {
+    pub fn parse(bytes: &'a [u8]) -> Result<Self, RegionError> {
+        let mut cursor = Cursor { bytes, position: 0 };
+        if cursor.take(4)? != MAGIC {
+            return Err(RegionError::InvalidMagic);
+        }
+        let version = u16::from_le_bytes(cursor.array()?);
+        if version != VERSION {
+            return Err(RegionError::UnsupportedVersion(version));
+        }
+        let region_pos = (cursor.read_i32()?, cursor.read_i32()?, cursor.read_i32()?);
+
+        let params_length = cursor.read_u32()? as usize;
+        let params = serde_json::from_slice(cursor.take(params_length)?)
+            .map_err(|err| RegionError::InvalidParams(err.to_string()))?;
+        let generator_length = cursor.read_u32()? as usize;
+        let generator = String::from_utf8(cursor.take(generator_length)?.to_vec())
+            .map_err(|_| RegionError::InvalidGenerator)?;
+
+        let edit_count = cursor.read_u32()? as usize;
+        let edit_data = cursor.take(edit_count.checked_mul(EDIT_BYTES).ok_or(RegionError::Truncated)?)?;
+        let edits: Vec<_> = edit_data.chunks_exact(EDIT_BYTES)
+            .map(|edit| {
+                let coordinate = |i: usize| i32::from_le_bytes(edit[i * 4..i * 4 + 4].try_into().unwrap());
+                let world_pos = (coordinate(0), coordinate(1), coordinate(2));
+                (world_pos, BlockId(u16::from_le_bytes([edit[12], edit[13]])))
+            })
+            .collect();
+
+        let mut index = Vec::with_capacity(INDEX_ENTRIES);
+        for _ in 0..INDEX_ENTRIES {
+            let offset = cursor.read_u32()? as usize;
+            let length = cursor.read_u32()? as usize;
+            if length > 0 && offset.checked_add(length).is_none_or(|end| end > bytes.len()) {
+                return Err(RegionError::Truncated);
+            }
+            index.push((offset, length));
+        }
+        for &(world_pos, _) in &edits {
+            let chunk_pos = chunk_of(world_pos);
+            if region_of(chunk_pos) != region_pos || index[index_slot(chunk_pos)].1 == 0 {
+                return Err(RegionError::InvalidEdit(world_pos));
+            }
+        }
+
+        Ok(Self {
+            bytes,
+            region_pos,
+            params,
+            generator,
+            edits,
+            index,
+        })
+    }
+
+    /// Terrain parameters and seed the region was generated with
+    pub fn params(&self) -> &TerrainParams {
+        &self.params
+    }
+
+    /// Name of the world generator the region was generated with
+    pub fn generator(&self) -> &str {
+        &self.generator
+    }
+
+    /// Player edits stored in this region
+    pub fn edits(&self) -> &[(BlockPos, BlockId)] {
+        &self.edits
+    }
+
+    /// Positions of the chunks stored in this region
+    pub fn chunk_positions(&self) -> Vec<ChunkPos> {
+        let base = (
+            self.region_pos.0 * REGION_SIZE,
+            self.region_pos.1 * REGION_SIZE,
+            self.region_pos.2 * REGION_SIZE,
+        );
+        let mut positions = Vec::new();
+        for x in 0..REGION_SIZE {
+            for y in 0..REGION_SIZE {
+                for z in 0..REGION_SIZE {
+                    let pos = (base.0 + x, base.1 + y, base.2 + z);
+                    if self.index[index_slot(pos)].1 > 0 {
+                        positions.push(pos);
+                    }
+                }
+            }
+        }
+        positions
+    }
+
+    /// Decodes the blocks of one chunk, or `None` when the region does not store it
+    pub fn read_chunk(&self, chunk_pos: ChunkPos) -> Result<Option<ChunkBlocks>, RegionError> {
+        if region_of(chunk_pos) != self.region_pos {
+            return Ok(None);
+        }
+        let (offset, length) = self.index[index_slot(chunk_pos)];
+        if length == 0 {
+            return Ok(None);
+        }
+
+        let payload = &self.bytes[offset..offset + length];
+        let data = &payload[1..];
+        let raw = match payload[0] {
+            COMPRESSION_NONE => data.to_vec(),
+            COMPRESSION_ZLIB => miniz_oxide::inflate::decompress_to_vec_zlib_with_limit(data, CHUNK_BYTES)
+                .map_err(|_| RegionError::InvalidChunk(chunk_pos))?,
+            _ => return Err(RegionError::InvalidChunk(chunk_pos)),
+        };
+        if raw.len() != CHUNK_BYTES {
+            return Err(RegionError::InvalidChunk(chunk_pos));
+        }
+
+        let mut blocks = Box::new([[[BlockId::AIR; 16]; 16]; 16]);
+        let mut ids = raw.chunks_exact(2).map(|id| u16::from_le_bytes([id[0], id[1]]));
+        for plane in blocks.iter_mut() {
+            for column in plane.iter_mut() {
+                for block in column.iter_mut() {
+                    *block = BlockId(ids.next().unwrap());
+                }
+            }
+        }
+        Ok(Some(blocks))
+    }
+}
+
+// Bounds-checked little-endian reads over the header
+struct Cursor<'a> {
+    bytes: &'a [u8],
+    position: usize,
+}
+
+impl<'a> Cursor<'a> {
+    fn take(&mut self, length: usize) -> Result<&'a [u8], RegionError> {
+        let end = self.position.checked_add(length).ok_or(RegionError::Truncated)?;
+        let slice = self.bytes.get(self.position..end).ok_or(RegionError::Truncated)?;
+        self.position = end;
+        Ok(slice)
+    }
+
+    fn array<const N: usize>(&mut self) -> Result<[u8; N], RegionError> {
+        Ok(self.take(N)?.try_into().unwrap())
+    }
+
+    fn read_u32(&mut self) -> Result<u32, RegionError> {
+        Ok(u32::from_le_bytes(self.array()?))
+    }
+
+    fn read_i32(&mut self) -> Result<i32, RegionError> {
+        Ok(i32::from_le_bytes(self.array()?))
+    }
+}
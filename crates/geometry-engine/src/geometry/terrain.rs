@@ -1,9 +1,15 @@
-use std::collections::HashMap;
-use super::voxel::VoxelChunk;
+use std::collections::{HashMap, HashSet};
+use super::blocks::{BlockId, BlockRegistry};
+use super::edits::EditLayer;
+use super::light;
+use super::noise::Fbm;
+use super::region::{self, ChunkBlocks, RegionError, RegionReader, RegionWriter};
+use super::voxel::{ChunkColumns, ChunkMesh, ChunkNeighbours, Face, MeshingMode, TextureAtlas, VoxelChunk};
+use super::worldgen::{WorldGenerator, WorldGenerators, DEFAULT_GENERATOR, DEFAULT_HEIGHT_RANGE};
 use serde::{Deserialize, Serialize};
 
-#[derive(Clone, Serialize, Deserialize)]
-#[serde(rename_all = "camelCase")]
+#[derive(Clone, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase", default)]
 pub struct TerrainParams {
     pub mountain_scale: f32,
     pub hills_scale: f32,
@@ -11,8 +17,24 @@ pub struct TerrainParams {
     pub sea_level: f32,
     pub tree_density: f32,
     pub cave_threshold: f32,
+    /// Size of the climate noise, roughly the width of a biome in blocks
     pub biome_scale: f32,
-    pub desert_threshold: f32,
+    /// Shifts the climate of every column colder (-1) or warmer (1)
+    pub temperature: f32,
+    /// Shifts the climate of every column drier (-1) or wetter (1)
+    pub humidity: f32,
+    /// World seed feeding every noise and placement hash
+    pub seed: u32,
+    /// Number of noise layers summed for mountains and hills
+    pub octaves: u32,
+    /// Frequency multiplier between successive octaves
+    pub lacunarity: f32,
+    /// Amplitude multiplier between successive octaves
+    pub gain: f32,
+    /// How far 3D noise bends the surface of density terrain into overhangs and arches (0-1)
+    pub overhang_strength: f32,
+    /// How common floating islands are above density terrain (0-1)
+    pub floating_island_frequency: f32,
 }
 
 impl Default for TerrainParams {
@@ -25,19 +47,180 @@ impl Default for TerrainParams {
             tree_density: 3.0,
             cave_threshold: 0.7,
             biome_scale: 200.0,
-            desert_threshold: 0.3,
+            temperature: 0.0,
+            humidity: 0.0,
+            seed: 0,
+            octaves: 4,
+            lacunarity: 2.0,
+            gain: 0.5,
+            overhang_strength: 0.5,
+            floating_island_frequency: 0.3,
         }
     }
 }
 
+impl TerrainParams {
+    /// Scrambled seed mixed into the noise lattice and tree placement hashes
+    pub fn seed_hash(&self) -> i32 {
+        self.seed.wrapping_mul(0x9E37_79B9) as i32
+    }
+    
+    pub fn fbm(&self) -> Fbm {
+        Fbm {
+            octaves: self.octaves.clamp(1, 8),
+            lacunarity: self.lacunarity,
+            gain: self.gain,
+        }
+    }
+}
+
+type ChunkPos = (i32, i32, i32);
+
+/// Chunk meshes that differ from what the previous `generate_chunk_meshes` call returned
+#[derive(Default)]
+pub struct ChunkMeshDiff {
+    pub added: Vec<(i32, i32, i32)>,
+    pub changed: Vec<(i32, i32, i32)>,
+    pub removed: Vec<(i32, i32, i32)>,
+    /// Meshes for every added or changed chunk
+    pub meshes: Vec<((i32, i32, i32), ChunkMesh)>,
+    /// Visible chunks still waiting to be generated
+    pub pending: usize,
+}
+
+/// Bounds on how many generated chunks are kept around once they leave the view
+#[derive(Clone, Copy, Debug)]
+pub struct CacheLimits {
+    /// Chunks further than this many chunks from the camera horizontally are dropped
+    pub unload_distance: i32,
+    /// Maximum number of cached chunks, 0 for no limit
+    pub max_chunks: usize,
+    /// Maximum approximate memory used by cached chunks, 0 for no limit
+    pub max_bytes: usize,
+}
+
+impl Default for CacheLimits {
+    fn default() -> Self {
+        Self {
+            unload_distance: 8,
+            max_chunks: 0,
+            max_bytes: 0,
+        }
+    }
+}
+
+/// Caps the work done per call when new chunks have to be generated
+#[derive(Clone, Copy, Debug, Default)]
+pub struct GenerationBudget {
+    /// Maximum number of chunks generated per call, 0 for no limit
+    pub max_chunks: usize,
+    /// Time after which no further chunks are started, 0 for no limit
+    pub max_millis: f64,
+}
+
+impl GenerationBudget {
+    fn is_exhausted(&self, generated: usize, started_at: f64) -> bool {
+        (self.max_chunks > 0 && generated >= self.max_chunks)
+            || (self.max_millis > 0.0 && now_millis() - started_at >= self.max_millis)
+    }
+}
+
+/// Camera view volume used to skip chunks that cannot be on screen.
+/// The frustum is approximated by a cone around `direction` that encloses it.
+#[derive(Clone, Copy, Debug)]
+pub struct ViewFrustum {
+    pub direction: [f32; 3],
+    /// Vertical field of view in degrees
+    pub fov_degrees: f32,
+    /// Width over height of the viewport
+    pub aspect: f32,
+    pub near: f32,
+    pub far: f32,
+}
+
+// Radius of the sphere enclosing a 16x16x16 chunk
+const CHUNK_BOUNDING_RADIUS: f32 = 8.0 * 1.732_050_8;
+// Radius of the circle enclosing a chunk's 16x16 footprint
+const CHUNK_FOOTPRINT_RADIUS: f32 = 8.0 * std::f32::consts::SQRT_2;
+// Longest ray `raycast` walks, far past any loaded chunk, so an unbounded distance still ends
+const MAX_RAYCAST_DISTANCE: f32 = 1024.0;
+
+impl ViewFrustum {
+    /// Whether a sphere at `offset` from the camera may intersect the view volume
+    fn intersects_sphere(&self, offset: [f32; 3], radius: f32) -> bool {
+        let [dx, dy, dz] = self.direction;
+        let length = (dx * dx + dy * dy + dz * dz).sqrt();
+        if length == 0.0 {
+            return true;
+        }
+        let distance = (offset[0] * offset[0] + offset[1] * offset[1] + offset[2] * offset[2]).sqrt();
+        if distance <= radius {
+            return true;
+        }
+        
+        // Depth along the view direction against the near and far planes
+        let depth = (offset[0] * dx + offset[1] * dy + offset[2] * dz) / length;
+        if depth + radius < self.near || depth - radius > self.far {
+            return false;
+        }
+        
+        // Cone through the frustum corners, widened by the sphere's angular radius
+        let half_height = (self.fov_degrees.to_radians() * 0.5).tan();
+        let half_angle = (half_height * (1.0 + self.aspect * self.aspect).sqrt()).atan();
+        let angle = (depth / distance).clamp(-1.0, 1.0).acos();
+        angle <= half_angle + (radius / distance).asin()
+    }
+}
+
+/// Block a ray stopped at: the first non-air one, or the first solid one when skipping
+/// non-solid blocks
+#[derive(Clone, Copy, Debug)]
+pub struct RaycastHit {
+    /// World position of the block that was hit
+    pub block_pos: (i32, i32, i32),
+    pub block: BlockId,
+    /// Side of the block the ray entered through
+    pub face: Face,
+    /// Empty cell in front of the hit face, where a new block would be placed
+    pub place_pos: (i32, i32, i32),
+    /// Distance along the ray to the hit face
+    pub distance: f32,
+}
+
 pub struct TerrainGenerator {
     camera_x: f32,
     camera_y: f32,
     camera_z: f32,
     radius: f32,
     render_distance: i32,
+    // Vertical extent of the world in blocks, `min_height` inclusive, `max_height` exclusive
+    min_height: i32,
+    max_height: i32,
+    frustum: Option<ViewFrustum>,
     params: TerrainParams,
+    registry: BlockRegistry,
+    generators: WorldGenerators,
+    // Name of the registered generator new chunks come from
+    generator: String,
+    meshing_mode: MeshingMode,
+    // Texture coordinates are only emitted while an atlas is set
+    texture_atlas: Option<TextureAtlas>,
+    cache_limits: CacheLimits,
+    generation_budget: GenerationBudget,
+    // Visible chunks skipped by the last update because the budget ran out
+    pending_chunks: usize,
+    // Incremented on every visibility update, used as the LRU clock
+    frame: u64,
     voxel_chunks: HashMap<(i32, i32, i32), VoxelChunk>,
+    // Surface of every column of chunks with a chunk loaded, keyed by chunk x and z
+    chunk_columns: HashMap<(i32, i32), ChunkColumns>,
+    // Player modifications, re-applied on top of every (re)generated chunk
+    edits: EditLayer,
+    // Blocks of chunks restored from region files, used in place of the generator until
+    // the terrain parameters or generator change
+    saved_chunks: HashMap<(i32, i32, i32), ChunkBlocks>,
+    // Chunks whose mesh the caller of `generate_chunk_meshes` currently holds
+    emitted_chunks: HashSet<(i32, i32, i32)>,
 }
 
 impl TerrainGenerator {
@@ -48,8 +231,24 @@ impl TerrainGenerator {
             camera_z: 0.0,
             radius: 15.0,
             render_distance: 1,
+            min_height: DEFAULT_HEIGHT_RANGE.0,
+            max_height: DEFAULT_HEIGHT_RANGE.1,
+            frustum: None,
             params: TerrainParams::default(),
+            registry: BlockRegistry::default(),
+            generators: WorldGenerators::default(),
+            generator: DEFAULT_GENERATOR.to_string(),
+            meshing_mode: MeshingMode::default(),
+            texture_atlas: None,
+            cache_limits: CacheLimits::default(),
+            generation_budget: GenerationBudget::default(),
+            pending_chunks: 0,
+            frame: 0,
             voxel_chunks: HashMap::new(),
+            chunk_columns: HashMap::new(),
+            edits: EditLayer::default(),
+            saved_chunks: HashMap::new(),
+            emitted_chunks: HashSet::new(),
         }
     }
 
@@ -60,58 +259,493 @@ impl TerrainGenerator {
         self.radius = radius;
     }
     
+    /// Restricts chunk selection to the given view volume, or to the full
+    /// render distance sphere when `None`
+    pub fn set_view_frustum(&mut self, frustum: Option<ViewFrustum>) {
+        self.frustum = frustum;
+    }
+    
     pub fn set_render_distance(&mut self, distance: i32) {
-        self.render_distance = distance.max(1).min(5);
-        // Clear chunks to force regeneration with new distance
-        self.voxel_chunks.clear();
+        // Chunks beyond a smaller distance stop being meshed but stay cached until they
+        // pass the cache's unload distance or it runs over budget
+        self.render_distance = distance.clamp(1, 5);
+    }
+    
+    /// Sets the vertical block range that is loaded in every visible column,
+    /// independent of the camera height
+    pub fn set_world_height_range(&mut self, min_height: i32, max_height: i32) {
+        self.min_height = min_height;
+        self.max_height = max_height.max(min_height + 1);
     }
     
     pub fn set_terrain_params(&mut self, params: TerrainParams) {
         self.params = params;
-        // Clear chunks to force regeneration with new parameters
+        // Clear chunks to force regeneration with new parameters; edits are kept
+        // in their own layer and re-applied as chunks are regenerated
+        self.voxel_chunks.clear();
+        self.chunk_columns.clear();
+        self.saved_chunks.clear();
+    }
+    
+    /// Replaces the block definitions. Chunks are regenerated since the terrain
+    /// blocks may have moved to other ids; edits keep their raw ids.
+    pub fn set_block_registry(&mut self, registry: BlockRegistry) {
+        self.registry = registry;
         self.voxel_chunks.clear();
     }
+    
+    /// Switches to the registered generator called `name` and regenerates the world,
+    /// keeping edits. The world height range becomes the one the generator suggests.
+    /// Returns false if there is no such generator.
+    pub fn set_world_generator(&mut self, name: &str) -> bool {
+        let Some(generator) = self.generators.get(name) else {
+            return false;
+        };
+        if self.generator != name {
+            let (min_height, max_height) = generator.height_range();
+            self.set_world_height_range(min_height, max_height);
+            self.generator = name.to_string();
+            self.voxel_chunks.clear();
+            self.chunk_columns.clear();
+            self.saved_chunks.clear();
+        }
+        true
+    }
+    
+    /// Adds a generator that `set_world_generator` can pick, replacing one of the same name
+    pub fn register_world_generator(&mut self, name: &str, generator: Box<dyn WorldGenerator>) {
+        let (min_height, max_height) = generator.height_range();
+        self.generators.register(name, generator);
+        if self.generator == name {
+            self.set_world_height_range(min_height, max_height);
+            self.voxel_chunks.clear();
+            self.chunk_columns.clear();
+            self.saved_chunks.clear();
+        }
+    }
+    
+    pub fn world_generator(&self) -> &str {
+        &self.generator
+    }
+    
+    pub fn world_generator_names(&self) -> Vec<String> {
+        self.generators.names()
+    }
+    
+    pub fn block_id(&self, name: &str) -> Option<BlockId> {
+        self.registry.id(name)
+    }
+    
+    pub fn set_meshing_mode(&mut self, mode: MeshingMode) {
+        if self.meshing_mode != mode {
+            self.meshing_mode = mode;
+            for chunk in self.voxel_chunks.values_mut() {
+                chunk.mark_dirty();
+            }
+        }
+    }
+    
+    pub fn set_texture_atlas(&mut self, atlas: Option<TextureAtlas>) {
+        if self.texture_atlas != atlas {
+            self.texture_atlas = atlas;
+            for chunk in self.voxel_chunks.values_mut() {
+                chunk.mark_dirty();
+            }
+        }
+    }
 
-    pub fn generate(&mut self) -> (Vec<f32>, Vec<u32>, Vec<f32>, Vec<f32>) {
-        let mut vertices = Vec::new();
-        let mut indices = Vec::new();
-        let mut normals = Vec::new();
-        let mut colors = Vec::new();
+    pub fn set_cache_limits(&mut self, limits: CacheLimits) {
+        self.cache_limits = limits;
+    }
+    
+    /// Block at a world position, generating its chunk if it is not loaded
+    pub fn get_block_at(&mut self, x: i32, y: i32, z: i32) -> BlockId {
+        let (chunk_pos, (lx, ly, lz)) = Self::world_to_chunk(x, y, z);
+        self.get_or_create_chunk(chunk_pos).get_block(lx, ly, lz)
+    }
+    
+    /// Replaces the block at a world position, generating its chunk if needed, relights
+    /// the surroundings and flags every chunk whose mesh can see the change for re-meshing.
+    /// The edit is recorded so it survives regeneration and eviction.
+    pub fn set_block_at(&mut self, x: i32, y: i32, z: i32, block: BlockId) {
+        self.edits.set((x, y, z), block);
+        let (chunk_pos, local) = Self::world_to_chunk(x, y, z);
+        self.get_or_create_chunk(chunk_pos);
+        self.voxel_chunks.get_mut(&chunk_pos).unwrap().set_block(local.0, local.1, local.2, block);
+        mark_block_dirty(&mut self.voxel_chunks, (x, y, z));
+        light::relight_block(&mut self.voxel_chunks, &self.registry, (x, y, z));
+    }
+    
+    /// Walks the voxel grid along a ray (Amanatides-Woo DDA) and returns the first
+    /// non-air block within `max_distance`, or the first solid one with `skip_non_solid`
+    /// to look through water and the like. Chunks that are not loaded count as empty.
+    /// Rays end after `MAX_RAYCAST_DISTANCE` blocks; non-finite inputs never hit anything.
+    pub fn raycast(
+        &self,
+        origin: [f32; 3],
+        direction: [f32; 3],
+        max_distance: f32,
+        skip_non_solid: bool,
+    ) -> Option<RaycastHit> {
+        if !origin.iter().chain(&direction).all(|value| value.is_finite()) || max_distance.is_nan() {
+            return None;
+        }
+        let max_distance = max_distance.min(MAX_RAYCAST_DISTANCE);
+        let length = (direction[0] * direction[0] + direction[1] * direction[1] + direction[2] * direction[2]).sqrt();
+        if length == 0.0 || !length.is_finite() {
+            return None;
+        }
+        let dir = direction.map(|d| d / length);
         
-        // Generate voxel chunks only
-        let chunk_positions = self.get_visible_chunk_positions();
-        for chunk_pos in chunk_positions {
-            let chunk = self.get_or_create_chunk(chunk_pos);
-            let (v, i, n, c) = chunk.generate_mesh();
+        let mut cell = origin.map(|o| o.floor() as i32);
+        let mut step = [0i32; 3];
+        // Ray distance at which the next cell boundary is crossed on each axis
+        let mut t_max = [f32::INFINITY; 3];
+        // Ray distance between two boundaries on each axis
+        let mut t_delta = [f32::INFINITY; 3];
+        for axis in 0..3 {
+            if dir[axis] > 0.0 {
+                step[axis] = 1;
+                t_max[axis] = (cell[axis] as f32 + 1.0 - origin[axis]) / dir[axis];
+                t_delta[axis] = 1.0 / dir[axis];
+            } else if dir[axis] < 0.0 {
+                step[axis] = -1;
+                t_max[axis] = (origin[axis] - cell[axis] as f32) / -dir[axis];
+                t_delta[axis] = -1.0 / dir[axis];
+            }
+        }
+        
+        // A ray starting inside a block hits the face it points out of
+        let mut distance = 0.0;
+        let mut face = Self::entry_face(Self::major_axis(dir), -step[Self::major_axis(dir)]);
+        loop {
+            let block = self.loaded_block_at(cell[0], cell[1], cell[2]);
+            let is_hit = if skip_non_solid {
+                self.registry.get(block).solid
+            } else {
+                block != BlockId::AIR
+            };
+            if is_hit {
+                let (nx, ny, nz) = face.normal();
+                return Some(RaycastHit {
+                    block_pos: (cell[0], cell[1], cell[2]),
+                    block,
+                    face,
+                    place_pos: (cell[0] + nx, cell[1] + ny, cell[2] + nz),
+                    distance,
+                });
+            }
             
-            // Offset indices and append to main geometry
-            let vertex_offset = (vertices.len() / 3) as u32;
-            vertices.extend(v);
-            for idx in i {
-                indices.push(idx + vertex_offset);
+            let axis = if t_max[0] < t_max[1] && t_max[0] < t_max[2] {
+                0
+            } else if t_max[1] < t_max[2] {
+                1
+            } else {
+                2
+            };
+            distance = t_max[axis];
+            if distance > max_distance {
+                return None;
+            }
+            cell[axis] = cell[axis].wrapping_add(step[axis]);
+            t_max[axis] += t_delta[axis];
+            face = Self::entry_face(axis, step[axis]);
+        }
+    }
+    
+    // Face of a cell that a ray moving by `step` along `axis` enters through
+    fn entry_face(axis: usize, step: i32) -> Face {
+        match (axis, step > 0) {
+            (0, true) => Face::Left,
+            (0, false) => Face::Right,
+            (1, true) => Face::Bottom,
+            (1, false) => Face::Top,
+            (_, true) => Face::Back,
+            (_, false) => Face::Front,
+        }
+    }
+    
+    fn major_axis(dir: [f32; 3]) -> usize {
+        let [x, y, z] = dir.map(f32::abs);
+        if x >= y && x >= z {
+            0
+        } else if y >= z {
+            1
+        } else {
+            2
+        }
+    }
+    
+    fn loaded_block_at(&self, x: i32, y: i32, z: i32) -> BlockId {
+        let (chunk_pos, (lx, ly, lz)) = Self::world_to_chunk(x, y, z);
+        self.voxel_chunks
+            .get(&chunk_pos)
+            .map_or(BlockId::AIR, |chunk| chunk.get_block(lx, ly, lz))
+    }
+    
+    pub fn edit_count(&self) -> usize {
+        self.edits.len()
+    }
+    
+    /// Forgets all player edits and chunks loaded from region files, and regenerates the
+    /// affected chunks from the terrain parameters
+    pub fn clear_edits(&mut self) {
+        for chunk_pos in self.edits.chunk_positions().chain(self.saved_chunks.keys().copied()) {
+            self.voxel_chunks.remove(&chunk_pos);
+        }
+        self.edits.clear();
+        self.saved_chunks.clear();
+    }
+
+    /// Encodes the world into region files keyed by region position. Only chunks carrying
+    /// player edits are stored, everything else regenerates from the saved seed and
+    /// parameters. The camera's region is always written so an unedited world keeps them too.
+    pub fn save_regions(&self) -> Vec<((i32, i32, i32), Vec<u8>)> {
+        let mut writers = HashMap::new();
+        let camera_region = region::region_of(self.camera_chunk());
+        writers.insert(camera_region, RegionWriter::new(camera_region, &self.params, &self.generator));
+
+        for chunk_pos in self.edits.chunk_positions() {
+            let region_pos = region::region_of(chunk_pos);
+            let writer = writers.entry(region_pos)
+                .or_insert_with(|| RegionWriter::new(region_pos, &self.params, &self.generator));
+            for (world_pos, block) in self.edits.chunk_edits(chunk_pos) {
+                writer.add_edit(world_pos, block);
+            }
+            match self.voxel_chunks.get(&chunk_pos) {
+                Some(chunk) => writer.add_chunk(chunk_pos, chunk),
+                None => {
+                    // Evicted since it was edited, rebuild it from its ground plus edits
+                    let mut chunk = self.generate_chunk(chunk_pos);
+                    self.edits.apply(chunk_pos, &mut chunk);
+                    writer.add_chunk(chunk_pos, &chunk);
+                }
             }
-            normals.extend(n);
-            colors.extend(c);
         }
 
-        (vertices, indices, normals, colors)
+        writers.into_iter()
+            .map(|(region_pos, writer)| (region_pos, writer.finish()))
+            .collect()
+    }
+
+    /// Restores one region written by `save_regions`. Switches to the region's terrain
+    /// parameters and world generator if they differ, then puts back its stored chunks as
+    /// they were saved along with their edits, replacing any edits already held for those
+    /// chunks. Nothing changes if the data is invalid.
+    /// Call `clear_edits` first to load a different world rather than merge into this one.
+    pub fn load_region(&mut self, bytes: &[u8]) -> Result<(), RegionError> {
+        let region = RegionReader::parse(bytes)?;
+        let mut stored = Vec::new();
+        for chunk_pos in region.chunk_positions() {
+            if let Some(blocks) = region.read_chunk(chunk_pos)? {
+                stored.push((chunk_pos, blocks));
+            }
+        }
+        if self.generators.get(region.generator()).is_none() {
+            return Err(RegionError::UnknownGenerator(region.generator().to_string()));
+        }
+
+        if *region.params() != self.params {
+            self.set_terrain_params(region.params().clone());
+        }
+        self.set_world_generator(region.generator());
+
+        for (chunk_pos, _) in &stored {
+            self.edits.clear_chunk(*chunk_pos);
+        }
+        for &(world_pos, block) in region.edits() {
+            self.edits.set(world_pos, block);
+        }
+        for (chunk_pos, blocks) in stored {
+            self.saved_chunks.insert(chunk_pos, blocks);
+            self.voxel_chunks.remove(&chunk_pos);
+            self.get_or_create_chunk(chunk_pos);
+        }
+        Ok(())
+    }
+
+    /// Writes every region from `save_regions` into `dir` as `r.<x>.<y>.<z>.vxr` files
+    #[cfg(not(target_arch = "wasm32"))]
+    pub fn save_to_dir(&self, dir: &std::path::Path) -> std::io::Result<()> {
+        std::fs::create_dir_all(dir)?;
+        for (region_pos, bytes) in self.save_regions() {
+            std::fs::write(dir.join(region::region_file_name(region_pos)), bytes)?;
+        }
+        Ok(())
+    }
+
+    /// Loads every region file found in `dir`
+    #[cfg(not(target_arch = "wasm32"))]
+    pub fn load_from_dir(&mut self, dir: &std::path::Path) -> std::io::Result<()> {
+        for entry in std::fs::read_dir(dir)? {
+            let path = entry?.path();
+            if path.extension().is_some_and(|extension| extension == "vxr") {
+                let bytes = std::fs::read(&path)?;
+                self.load_region(&bytes)
+                    .map_err(|err| std::io::Error::new(std::io::ErrorKind::InvalidData, err))?;
+            }
+        }
+        Ok(())
+    }
+
+    // Splits a world block position into its chunk position and the offset inside that chunk
+    fn world_to_chunk(x: i32, y: i32, z: i32) -> (ChunkPos, (usize, usize, usize)) {
+        (
+            (x.div_euclid(16), y.div_euclid(16), z.div_euclid(16)),
+            (x.rem_euclid(16) as usize, y.rem_euclid(16) as usize, z.rem_euclid(16) as usize),
+        )
+    }
+    
+    pub fn set_generation_budget(&mut self, budget: GenerationBudget) {
+        self.generation_budget = budget;
+    }
+    
+    /// Visible chunks that were not generated yet because of the generation budget
+    pub fn pending_chunk_count(&self) -> usize {
+        self.pending_chunks
+    }
+    
+    pub fn cached_chunk_count(&self) -> usize {
+        self.voxel_chunks.len()
+    }
+    
+    pub fn cache_size_bytes(&self) -> usize {
+        self.voxel_chunks.values().map(VoxelChunk::memory_usage).sum()
+    }
+
+    pub fn generate(&mut self) -> ChunkMesh {
+        let mut geometry = ChunkMesh::default();
+        
+        let (chunk_positions, _) = self.update_visible_chunks();
+        for chunk_pos in chunk_positions {
+            // Offset indices and append to main geometry
+            geometry.append(self.voxel_chunks[&chunk_pos].mesh());
+        }
+
+        geometry
+    }
+    
+    /// Per-chunk alternative to `generate`: only returns meshes that were added or
+    /// changed since the previous call, plus the chunks the caller should drop.
+    pub fn generate_chunk_meshes(&mut self) -> ChunkMeshDiff {
+        let mut diff = ChunkMeshDiff::default();
+        let (chunk_positions, rebuilt) = self.update_visible_chunks();
+        
+        // Chunks without any visible faces are not sent at all
+        let visible: HashSet<_> = chunk_positions.into_iter()
+            .filter(|pos| !self.voxel_chunks[pos].mesh().is_empty())
+            .collect();
+        
+        diff.removed = self.emitted_chunks.difference(&visible).copied().collect();
+        
+        for &pos in &visible {
+            if !self.emitted_chunks.contains(&pos) {
+                diff.added.push(pos);
+            } else if rebuilt.contains(&pos) {
+                diff.changed.push(pos);
+            } else {
+                continue;
+            }
+            diff.meshes.push((pos, self.voxel_chunks[&pos].mesh().clone()));
+        }
+        
+        self.emitted_chunks = visible;
+        diff.pending = self.pending_chunks;
+        diff
+    }
+    
+    /// Loads visible chunks nearest to the camera first, within the generation budget,
+    /// and re-meshes the dirty ones.
+    /// Returns the loaded visible positions and the chunks whose mesh changed.
+    fn update_visible_chunks(&mut self) -> (Vec<ChunkPos>, HashSet<ChunkPos>) {
+        // Generate voxel chunks only
+        let mut visible_positions = self.get_visible_chunk_positions();
+        let (cx, cy, cz) = self.camera_chunk();
+        visible_positions.sort_by_key(|pos| {
+            let (dx, dy, dz) = (pos.0 - cx, pos.1 - cy, pos.2 - cz);
+            dx * dx + dy * dy + dz * dz
+        });
+        
+        // Create every chunk first so border faces can be culled against loaded neighbours
+        self.frame += 1;
+        let started_at = now_millis();
+        let mut generated = 0;
+        let mut chunk_positions = Vec::with_capacity(visible_positions.len());
+        self.pending_chunks = 0;
+        for chunk_pos in visible_positions {
+            if !self.voxel_chunks.contains_key(&chunk_pos) {
+                if self.generation_budget.is_exhausted(generated, started_at) {
+                    self.pending_chunks += 1;
+                    continue;
+                }
+                self.get_or_create_chunk(chunk_pos);
+                generated += 1;
+            }
+            self.voxel_chunks.get_mut(&chunk_pos).unwrap().touch(self.frame);
+            chunk_positions.push(chunk_pos);
+        }
+        self.evict_chunks();
+        
+        let mut rebuilt = HashSet::new();
+        for &chunk_pos in &chunk_positions {
+            let chunk = &self.voxel_chunks[&chunk_pos];
+            if !chunk.is_dirty() {
+                continue;
+            }
+            let neighbours = ChunkNeighbours::new(chunk_pos, |pos| self.voxel_chunks.get(&pos));
+            let mesh = chunk.generate_mesh(self.meshing_mode, &neighbours, &self.registry, self.texture_atlas.as_ref());
+            if self.voxel_chunks.get_mut(&chunk_pos).unwrap().set_mesh(mesh) {
+                rebuilt.insert(chunk_pos);
+            }
+        }
+        
+        (chunk_positions, rebuilt)
+    }
+    
+    fn camera_chunk(&self) -> (i32, i32, i32) {
+        (
+            (self.camera_x / 16.0).floor() as i32,
+            (self.camera_y / 16.0).floor() as i32,
+            (self.camera_z / 16.0).floor() as i32,
+        )
     }
     
     fn get_visible_chunk_positions(&self) -> Vec<(i32, i32, i32)> {
         let mut positions = Vec::new();
         
-        // Generate chunks around camera position including vertical chunks
-        let chunk_x = (self.camera_x / 16.0).floor() as i32;
-        let chunk_y = (self.camera_y / 16.0).floor() as i32;
-        let chunk_z = (self.camera_z / 16.0).floor() as i32;
+        // Generate full world-height columns of chunks around the camera
+        let (chunk_x, _, chunk_z) = self.camera_chunk();
+        let min_chunk_y = self.min_height.div_euclid(16);
+        let max_chunk_y = (self.max_height - 1).div_euclid(16);
         
         let dist = self.render_distance;
         
+        // Columns fully outside this circle are never visible
+        let view_distance = (dist + 1) as f32 * 16.0;
+        
         // Generate grid of chunks around camera based on render distance
         for dx in -dist..=dist {
-            for dy in -(dist + 1)..=(dist + 1) {  // More vertical range to see terrain variation
+            for chunk_y in min_chunk_y..=max_chunk_y {
                 for dz in -dist..=dist {
-                    positions.push((chunk_x + dx, chunk_y + dy, chunk_z + dz));
+                    let pos = (chunk_x + dx, chunk_y, chunk_z + dz);
+                    let offset = [
+                        pos.0 as f32 * 16.0 + 8.0 - self.camera_x,
+                        pos.1 as f32 * 16.0 + 8.0 - self.camera_y,
+                        pos.2 as f32 * 16.0 + 8.0 - self.camera_z,
+                    ];
+                    let horizontal_distance = (offset[0] * offset[0] + offset[2] * offset[2]).sqrt();
+                    if horizontal_distance - CHUNK_FOOTPRINT_RADIUS > view_distance {
+                        continue;
+                    }
+                    let distance = (offset[0] * offset[0] + offset[1] * offset[1] + offset[2] * offset[2]).sqrt();
+                    
+                    // Chunks within the camera radius stay loaded even behind the camera,
+                    // so turning around does not reveal holes right next to the viewer
+                    let in_frustum = self.frustum
+                        .is_none_or(|frustum| frustum.intersects_sphere(offset, CHUNK_BOUNDING_RADIUS));
+                    if in_frustum || distance - CHUNK_BOUNDING_RADIUS <= self.radius {
+                        positions.push(pos);
+                    }
                 }
             }
         }
@@ -121,121 +755,414 @@ impl TerrainGenerator {
     
     fn get_or_create_chunk(&mut self, pos: (i32, i32, i32)) -> &VoxelChunk {
         if !self.voxel_chunks.contains_key(&pos) {
-            let params = self.params.clone();
-            
-            // Create a closure that captures the terrain calculation
-            let chunk = VoxelChunk::new_with_terrain_params(
-                pos.0, pos.1, pos.2,
-                params,
-                |x, z, params| {
-                    let mut height = 0.0;
-                    
-                    // Large scale terrain features (mountains and valleys)
-                    height += Self::noise2d_static(x * 0.003, z * 0.003) * params.mountain_scale;
-                    
-                    // Medium scale hills
-                    height += Self::noise2d_static(x * 0.01, z * 0.01) * params.hills_scale;
-                    
-                    // Small scale bumps (roughness)
-                    height += Self::noise2d_static(x * 0.05, z * 0.05) * params.roughness;
-                    
-                    // Tiny details
-                    height += Self::noise2d_static(x * 0.1, z * 0.1) * 1.0;
-                    
-                    // Create some plateaus and cliffs
-                    let plateau = Self::noise2d_static(x * 0.002, z * 0.002);
-                    if plateau > 0.3 {
-                        height += 20.0;
-                    }
-                    
-                    height
-                },
-                |x, z, params| {
-                    // Biome function with scale parameter
-                    let scale = 1.0 / params.biome_scale;
-                    Self::noise2d_static(x * scale, z * scale)
-                },
-                |x, y, z, _params| {
-                    // 3D noise for cave generation
-                    let noise1 = Self::noise3d_static(x * 0.05, y * 0.05, z * 0.05);
-                    let noise2 = Self::noise3d_static(x * 0.1, y * 0.1, z * 0.1) * 0.5;
-                    noise1 + noise2
-                }
-            );
+            let generator = self.generators.get(&self.generator).expect("active world generator is registered");
+            self.chunk_columns
+                .entry((pos.0, pos.2))
+                .or_insert_with(|| ChunkColumns::sample(pos.0, pos.2, &self.params, generator));
+            let mut chunk = self.generate_chunk(pos);
+            self.edits.apply(pos, &mut chunk);
             self.voxel_chunks.insert(pos, chunk);
+            self.mark_neighbours_dirty(pos);
+            light::light_new_chunk(&mut self.voxel_chunks, &self.registry, pos);
         }
         self.voxel_chunks.get(&pos).unwrap()
     }
     
-    // Static version of noise functions for use in closures
-    fn noise2d_static(x: f32, y: f32) -> f32 {
-        let ix = x.floor() as i32;
-        let iy = y.floor() as i32;
-        let fx = x - x.floor();
-        let fy = y - y.floor();
-        
-        let a = Self::hash2d_static(ix, iy);
-        let b = Self::hash2d_static(ix + 1, iy);
-        let c = Self::hash2d_static(ix, iy + 1);
-        let d = Self::hash2d_static(ix + 1, iy + 1);
-        
-        let ux = fx * fx * (3.0 - 2.0 * fx);
-        let uy = fy * fy * (3.0 - 2.0 * fy);
-        
-        let x1 = a * (1.0 - ux) + b * ux;
-        let x2 = c * (1.0 - ux) + d * ux;
-        
-        x1 * (1.0 - uy) + x2 * uy
-    }
-    
-    fn hash2d_static(x: i32, y: i32) -> f32 {
-        let mut n = x + y * 57;
-        n = (n << 13) ^ n;
-        let m = (n * (n * n * 15731 + 789221) + 1376312589) & 0x7fffffff;
-        1.0 - (m as f32) / 1073741824.0
+    // Terrain of a chunk straight from the generator, without player edits. The ground
+    // is filled first, then every feature reaching into the chunk is stamped on top.
+    // Chunks loaded from a region keep the blocks they were saved with instead.
+    fn generate_chunk(&self, pos: (i32, i32, i32)) -> VoxelChunk {
+        if let Some(blocks) = self.saved_chunks.get(&pos) {
+            return VoxelChunk::from_blocks(pos, blocks);
+        }
+        let generator = self.generators.get(&self.generator).expect("active world generator is registered");
+        let blocks = self.registry.terrain_blocks();
+        let sampled;
+        let columns = match self.chunk_columns.get(&(pos.0, pos.2)) {
+            Some(columns) => columns,
+            None => {
+                sampled = ChunkColumns::sample(pos.0, pos.2, &self.params, generator);
+                &sampled
+            }
+        };
+        let mut chunk = VoxelChunk::new_with_terrain_params(pos.0, pos.1, pos.2, columns, &self.params, blocks, generator);
+        for feature in generator.decorations(pos, &self.params) {
+            feature.write_into(pos, &mut chunk, &blocks);
+        }
+        chunk
     }
     
-    fn noise3d_static(x: f32, y: f32, z: f32) -> f32 {
-        let ix = x.floor() as i32;
-        let iy = y.floor() as i32;
-        let iz = z.floor() as i32;
-        let fx = x - x.floor();
-        let fy = y - y.floor();
-        let fz = z - z.floor();
-        
-        // 8 corner points of the cube
-        let a = Self::hash3d_static(ix, iy, iz);
-        let b = Self::hash3d_static(ix + 1, iy, iz);
-        let c = Self::hash3d_static(ix, iy + 1, iz);
-        let d = Self::hash3d_static(ix + 1, iy + 1, iz);
-        let e = Self::hash3d_static(ix, iy, iz + 1);
-        let f = Self::hash3d_static(ix + 1, iy, iz + 1);
-        let g = Self::hash3d_static(ix, iy + 1, iz + 1);
-        let h = Self::hash3d_static(ix + 1, iy + 1, iz + 1);
+    /// Drops chunks outside the unload distance, then the least recently used ones
+    /// until the cache fits its budget. Chunks visible this frame are never evicted.
+    fn evict_chunks(&mut self) {
+        let camera_chunk = self.camera_chunk();
+        let limits = self.cache_limits;
         
-        // Smooth interpolation factors
-        let ux = fx * fx * (3.0 - 2.0 * fx);
-        let uy = fy * fy * (3.0 - 2.0 * fy);
-        let uz = fz * fz * (3.0 - 2.0 * fz);
-        
-        // Interpolate along x axis
-        let x1 = a * (1.0 - ux) + b * ux;
-        let x2 = c * (1.0 - ux) + d * ux;
-        let x3 = e * (1.0 - ux) + f * ux;
-        let x4 = g * (1.0 - ux) + h * ux;
+        let mut evicted: Vec<_> = self.voxel_chunks.iter()
+            .filter(|(pos, chunk)| {
+                let distance = (pos.0 - camera_chunk.0).abs().max((pos.2 - camera_chunk.2).abs());
+                chunk.last_used() != self.frame && distance > limits.unload_distance
+            })
+            .map(|(pos, _)| *pos)
+            .collect();
+        for pos in &evicted {
+            self.voxel_chunks.remove(pos);
+        }
         
-        // Interpolate along y axis
-        let y1 = x1 * (1.0 - uy) + x2 * uy;
-        let y2 = x3 * (1.0 - uy) + x4 * uy;
+        let mut count = self.voxel_chunks.len();
+        let mut bytes = self.cache_size_bytes();
+        let over_budget = |count: usize, bytes: usize| {
+            (limits.max_chunks > 0 && count > limits.max_chunks)
+                || (limits.max_bytes > 0 && bytes > limits.max_bytes)
+        };
+        if over_budget(count, bytes) {
+            let mut candidates: Vec<_> = self.voxel_chunks.iter()
+                .filter(|(_, chunk)| chunk.last_used() != self.frame)
+                .map(|(pos, chunk)| (chunk.last_used(), *pos))
+                .collect();
+            candidates.sort_unstable();
+            
+            for (_, pos) in candidates {
+                if !over_budget(count, bytes) {
+                    break;
+                }
+                if let Some(chunk) = self.voxel_chunks.remove(&pos) {
+                    count -= 1;
+                    bytes -= chunk.memory_usage();
+                    evicted.push(pos);
+                }
+            }
+        }
         
-        // Interpolate along z axis
-        y1 * (1.0 - uz) + y2 * uz
+        if !evicted.is_empty() {
+            let loaded: HashSet<_> = self.voxel_chunks.keys().map(|pos| (pos.0, pos.2)).collect();
+            self.chunk_columns.retain(|column, _| loaded.contains(column));
+        }
+        for pos in evicted {
+            self.mark_neighbours_dirty(pos);
+        }
     }
     
-    fn hash3d_static(x: i32, y: i32, z: i32) -> f32 {
-        let mut n = x + y * 57 + z * 131;
-        n = (n << 13) ^ n;
-        let m = (n * (n * n * 15731 + 789221) + 1376312589) & 0x7fffffff;
-        1.0 - (m as f32) / 1073741824.0
+    // Border faces, corner shading and light of the surrounding chunks may have changed
+    fn mark_neighbours_dirty(&mut self, pos: (i32, i32, i32)) {
+        for dx in -1..=1 {
+            for dy in -1..=1 {
+                for dz in -1..=1 {
+                    if (dx, dy, dz) == (0, 0, 0) {
+                        continue;
+                    }
+                    if let Some(chunk) = self.voxel_chunks.get_mut(&(pos.0 + dx, pos.1 + dy, pos.2 + dz)) {
+                        chunk.mark_dirty();
+                    }
+                }
+            }
+        }
+    }
+}
+
+// Flags the chunk holding a block for re-meshing, along with every neighbour whose
+// mesh samples that block at its border for face culling or shading
+fn mark_block_dirty(chunks: &mut HashMap<(i32, i32, i32), VoxelChunk>, pos: (i32, i32, i32)) {
+    let chunk_pos = (pos.0.div_euclid(16), pos.1.div_euclid(16), pos.2.div_euclid(16));
+    let local = [pos.0.rem_euclid(16), pos.1.rem_euclid(16), pos.2.rem_euclid(16)];
+    let span = |l: i32| match l {
+        0 => -1..=0,
+        15 => 0..=1,
+        _ => 0..=0,
+    };
+    for dx in span(local[0]) {
+        for dy in span(local[1]) {
+            for dz in span(local[2]) {
+                if let Some(chunk) = chunks.get_mut(&(chunk_pos.0 + dx, chunk_pos.1 + dy, chunk_pos.2 + dz)) {
+                    chunk.mark_dirty();
+                }
+            }
+        }
+    }
+}
+
+// Milliseconds since an arbitrary origin; `std::time::Instant` is unavailable in the browser
+#[cfg(target_arch = "wasm32")]
+fn now_millis() -> f64 {
+    js_sys::Date::now()
+}
+
+#[cfg(not(target_arch = "wasm32"))]
+fn now_millis() -> f64 {
+    std::time::SystemTime::now()
+        .duration_since(std::time::UNIX_EPOCH)
+        .map_or(0.0, |elapsed| elapsed.as_secs_f64() * 1000.0)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // Flips a block between air and stone, or writes back the block already there
+    fn edit(terrain: &mut TerrainGenerator, pos: (i32, i32, i32), keep: bool) {
+        let current = terrain.get_block_at(pos.0, pos.1, pos.2);
+        let block = match (keep, current) {
+            (true, _) => current,
+            (false, BlockId::AIR) => BlockId(1),
+            (false, _) => BlockId::AIR,
+        };
+        terrain.set_block_at(pos.0, pos.1, pos.2, block);
+    }
+
+    fn edited_world() -> (TerrainGenerator, Vec<(i32, i32, i32)>) {
+        let mut terrain = TerrainGenerator::new();
+        terrain.set_terrain_params(TerrainParams { seed: 7, ..TerrainParams::default() });
+        let edits = vec![(3, 2, 3), (4, 40, 4), (-3, 20, 130), (-3, 21, 130), (200, -5, -17)];
+        for (i, &pos) in edits.iter().enumerate() {
+            edit(&mut terrain, pos, i % 2 == 0);
+        }
+        (terrain, edits)
+    }
+
+    // FNV-1a over the block ids of a generated chunk, in x, y, z order
+    fn chunk_hash(seed: u32, chunk_pos: (i32, i32, i32)) -> u64 {
+        let mut terrain = TerrainGenerator::new();
+        terrain.set_terrain_params(TerrainParams { seed, ..TerrainParams::default() });
+        let chunk = terrain.generate_chunk(chunk_pos);
+        let mut hash = 0xcbf2_9ce4_8422_2325u64;
+        for x in 0..16 {
+            for y in 0..16 {
+                for z in 0..16 {
+                    for byte in chunk.get_block(x, y, z).0.to_le_bytes() {
+                        hash = (hash ^ byte as u64).wrapping_mul(0x0100_0000_01b3);
+                    }
+                }
+            }
+        }
+        hash
+    }
+
+    // Surface, cave, underwater and treetop chunks for a few seeds. Generation must stay
+    // bit-identical on every platform, so these only change when the terrain is meant to.
+    const GOLDEN_CHUNKS: [(u32, (i32, i32, i32), u64); 6] = [
+        (0, (0, 0, 0), 0xaca7_440a_6234_5f2e),
+        (0, (3, -2, -5), 0xe394_fd35_45f9_b4d4),
+        (1, (0, 0, 0), 0xa00f_6b02_5b3f_abf1),
+        (1, (5, -1, -3), 0xe6c2_a1ff_3b12_a745),
+        (12345, (0, 0, 0), 0x31d3_4301_f03f_fb66),
+        (12345, (20, 1, -20), 0xe207_7ddc_085b_411a),
+    ];
+
+    #[test]
+    fn seeded_chunks_match_golden_hashes() {
+        for (seed, chunk_pos, expected) in GOLDEN_CHUNKS {
+            assert_eq!(chunk_hash(seed, chunk_pos), expected, "seed {} chunk {:?}", seed, chunk_pos);
+        }
+    }
+
+    #[test]
+    fn different_seeds_give_different_chunks() {
+        for chunk_pos in [(0, 0, 0), (3, -2, -5), (-8, -1, 6)] {
+            assert_ne!(chunk_hash(0, chunk_pos), chunk_hash(1, chunk_pos), "chunk {:?}", chunk_pos);
+        }
+    }
+
+    const STONE: BlockId = BlockId(1);
+    const WATER: BlockId = BlockId(4);
+    const GLOWSTONE: BlockId = BlockId(8);
+
+    // Blocks placed high above the default terrain, where everything else is air
+    fn sky_with(blocks: &[((i32, i32, i32), BlockId)]) -> TerrainGenerator {
+        let mut terrain = TerrainGenerator::new();
+        for &((x, y, z), block) in blocks {
+            terrain.set_block_at(x, y, z, block);
+        }
+        terrain
+    }
+
+    fn hit(terrain: &TerrainGenerator, origin: [f32; 3], direction: [f32; 3]) -> RaycastHit {
+        terrain.raycast(origin, direction, 32.0, false).expect("ray hits a block")
+    }
+
+    #[test]
+    fn raycast_axis_aligned() {
+        let terrain = sky_with(&[((5, 120, 0), STONE), ((0, 115, 0), STONE)]);
+
+        let along_x = hit(&terrain, [0.5, 120.5, 0.5], [1.0, 0.0, 0.0]);
+        assert_eq!(along_x.block_pos, (5, 120, 0));
+        assert_eq!(along_x.block, STONE);
+        assert_eq!(along_x.face, Face::Left);
+        assert_eq!(along_x.place_pos, (4, 120, 0));
+        assert_eq!(along_x.distance, 4.5);
+
+        let down = hit(&terrain, [0.5, 120.5, 0.5], [0.0, -3.0, 0.0]);
+        assert_eq!(down.block_pos, (0, 115, 0));
+        assert_eq!(down.face, Face::Top);
+        assert_eq!(down.place_pos, (0, 116, 0));
+        assert_eq!(down.distance, 4.5);
+
+        assert!(terrain.raycast([0.5, 120.5, 0.5], [1.0, 0.0, 0.0], 4.0, false).is_none());
+        assert!(terrain.raycast([0.5, 120.5, 0.5], [0.0, 0.0, 1.0], 32.0, false).is_none());
+    }
+
+    #[test]
+    fn raycast_diagonal() {
+        // Along (2, 1, 0) the ray reaches x = 4 at y = 122.25, after crossing y = 122 at x = 3.5
+        let mut terrain = sky_with(&[((4, 122, 0), STONE)]);
+        let side = hit(&terrain, [0.5, 120.5, 0.5], [2.0, 1.0, 0.0]);
+        assert_eq!(side.block_pos, (4, 122, 0));
+        assert_eq!(side.face, Face::Left);
+        assert_eq!(side.place_pos, (3, 122, 0));
+        assert!((side.distance - 3.5 * 5f32.sqrt() / 2.0).abs() < 1e-5);
+
+        terrain.set_block_at(3, 122, 0, STONE);
+        let below = hit(&terrain, [0.5, 120.5, 0.5], [2.0, 1.0, 0.0]);
+        assert_eq!(below.block_pos, (3, 122, 0));
+        assert_eq!(below.face, Face::Bottom);
+        assert_eq!(below.place_pos, (3, 121, 0));
+        assert!((below.distance - 1.5 * 5f32.sqrt()).abs() < 1e-5);
     }
-}
\ No newline at end of file
+
+    #[test]
+    fn raycast_starting_inside_a_block() {
+        let terrain = sky_with(&[((0, 120, 0), STONE)]);
+        let inside = hit(&terrain, [0.5, 120.5, 0.5], [0.0, 0.2, 1.0]);
+        assert_eq!(inside.block_pos, (0, 120, 0));
+        assert_eq!(inside.face, Face::Front);
+        assert_eq!(inside.place_pos, (0, 120, 1));
+        assert_eq!(inside.distance, 0.0);
+    }
+
+    #[test]
+    fn raycast_negative_coordinates_and_chunk_borders() {
+        let terrain = sky_with(&[
+            ((-4, 120, -1), STONE),
+            ((16, 120, 8), STONE),
+            ((-1, 121, 8), STONE),
+            ((5, 118, -17), STONE),
+        ]);
+
+        let negative = hit(&terrain, [-0.5, 120.5, -0.5], [-1.0, 0.0, 0.0]);
+        assert_eq!(negative.block_pos, (-4, 120, -1));
+        assert_eq!(negative.face, Face::Right);
+        assert_eq!(negative.place_pos, (-3, 120, -1));
+        assert_eq!(negative.distance, 2.5);
+
+        // Into the next chunk along +x, and back across x = 0 into chunk -1
+        let east = hit(&terrain, [15.5, 120.5, 8.5], [1.0, 0.0, 0.0]);
+        assert_eq!((east.block_pos, east.face, east.place_pos), ((16, 120, 8), Face::Left, (15, 120, 8)));
+        assert_eq!(east.distance, 0.5);
+        let west = hit(&terrain, [0.5, 121.5, 8.5], [-1.0, 0.0, 0.0]);
+        assert_eq!((west.block_pos, west.face, west.place_pos), ((-1, 121, 8), Face::Right, (0, 121, 8)));
+        assert_eq!(west.distance, 0.5);
+
+        // Across z = -16 from chunk -1 into chunk -2
+        let south = hit(&terrain, [5.5, 118.5, -15.5], [0.0, 0.0, -1.0]);
+        assert_eq!((south.block_pos, south.face, south.place_pos), ((5, 118, -17), Face::Front, (5, 118, -16)));
+        assert_eq!(south.distance, 0.5);
+    }
+
+    #[test]
+    fn raycast_skips_non_solid_blocks_on_request() {
+        let terrain = sky_with(&[((2, 120, 0), WATER), ((5, 120, 0), STONE)]);
+        let water = hit(&terrain, [0.5, 120.5, 0.5], [1.0, 0.0, 0.0]);
+        assert_eq!(water.block, WATER);
+        let stone = terrain.raycast([0.5, 120.5, 0.5], [1.0, 0.0, 0.0], 32.0, true).unwrap();
+        assert_eq!(stone.block_pos, (5, 120, 0));
+    }
+
+    #[test]
+    fn raycast_rejects_non_finite_input() {
+        let terrain = sky_with(&[((5, 120, 0), STONE)]);
+        let origin = [0.5, 120.5, 0.5];
+        assert_eq!(terrain.raycast(origin, [1.0, 0.0, 0.0], f32::INFINITY, false).unwrap().block_pos, (5, 120, 0));
+        assert!(terrain.raycast(origin, [0.0, 1.0, 0.0], f32::INFINITY, false).is_none());
+        assert!(terrain.raycast(origin, [1.0, 0.0, 0.0], f32::NAN, false).is_none());
+        assert!(terrain.raycast([f32::NAN, 120.5, 0.5], [1.0, 0.0, 0.0], 32.0, false).is_none());
+        assert!(terrain.raycast(origin, [f32::NAN, 0.0, 0.0], 32.0, false).is_none());
+        assert!(terrain.raycast(origin, [f32::INFINITY, 0.0, 0.0], 32.0, false).is_none());
+        assert!(terrain.raycast(origin, [0.0, 0.0, 0.0], 32.0, false).is_none());
+    }
+
+    #[test]
+    fn edits_survive_regeneration_and_eviction() {
+        let edits = [((3, 120, 3), STONE), ((2, -20, 2), GLOWSTONE), ((-17, 5, 30), BlockId::AIR)];
+        let mut terrain = sky_with(&edits);
+        let assert_edits = |terrain: &mut TerrainGenerator| {
+            assert_eq!(terrain.edit_count(), edits.len());
+            for ((x, y, z), block) in edits {
+                assert_eq!(terrain.get_block_at(x, y, z), block, "edit at {:?}", (x, y, z));
+            }
+        };
+        assert_edits(&mut terrain);
+
+        terrain.set_terrain_params(TerrainParams { seed: 99, ..TerrainParams::default() });
+        assert_edits(&mut terrain);
+
+        assert!(terrain.set_world_generator("flat"));
+        assert_edits(&mut terrain);
+
+        // Least recently used chunks make room once the camera has moved far away
+        terrain.set_cache_limits(CacheLimits { unload_distance: 1000, max_chunks: 1, max_bytes: 0 });
+        terrain.update_camera(2000.0, 0.0, 2000.0, 20.0);
+        terrain.generate_chunk_meshes();
+        for ((x, y, z), _) in edits {
+            assert!(!terrain.voxel_chunks.contains_key(&TerrainGenerator::world_to_chunk(x, y, z).0));
+        }
+        assert_edits(&mut terrain);
+    }
+
+    #[test]
+    fn region_round_trip_restores_edits_verbatim() {
+        let (mut saved, edits) = edited_world();
+        let regions = saved.save_regions();
+        assert!(regions.len() >= 3);
+
+        let mut loaded = TerrainGenerator::new();
+        for (_, bytes) in &regions {
+            loaded.load_region(bytes).unwrap();
+        }
+        assert!(loaded.params == saved.params);
+        assert_eq!(loaded.edit_count(), edits.len());
+        for (x, y, z) in edits {
+            assert_eq!(loaded.get_block_at(x, y, z), saved.get_block_at(x, y, z));
+        }
+    }
+
+    #[test]
+    fn loaded_chunks_keep_their_saved_blocks() {
+        // A chunk of stone high up in the air, which the generator would leave empty
+        let chunk_pos = (0, 6, 0);
+        let stone = VoxelChunk::from_blocks(chunk_pos, &[[[BlockId(1); 16]; 16]; 16]);
+        let mut writer = RegionWriter::new(region::region_of(chunk_pos), &TerrainParams::default(), DEFAULT_GENERATOR);
+        writer.add_chunk(chunk_pos, &stone);
+        writer.add_edit((3, 100, 4), BlockId::AIR);
+        let bytes = writer.finish();
+
+        let mut terrain = TerrainGenerator::new();
+        terrain.load_region(&bytes).unwrap();
+        assert_eq!(terrain.edit_count(), 1);
+        assert_eq!(terrain.get_block_at(3, 100, 4), BlockId::AIR);
+        assert_eq!(terrain.get_block_at(3, 101, 4), BlockId(1));
+
+        // Other format versions are refused
+        let mut other_version = bytes.clone();
+        other_version[4] = 2;
+        assert!(matches!(TerrainGenerator::new().load_region(&other_version), Err(RegionError::UnsupportedVersion(2))));
+
+        terrain.clear_edits();
+        assert_eq!(terrain.get_block_at(3, 101, 4), BlockId::AIR);
+    }
+
+    #[test]
+    fn truncated_region_is_rejected() {
+        let (saved, _) = edited_world();
+        let (_, bytes) = saved.save_regions()
+            .into_iter()
+            .find(|(region_pos, _)| *region_pos == (-1, 0, 1))
+            .unwrap();
+
+        let mut loaded = TerrainGenerator::new();
+        for length in 0..bytes.len() {
+            assert!(loaded.load_region(&bytes[..length]).is_err(), "accepted {} of {} bytes", length, bytes.len());
+        }
+        assert_eq!(loaded.edit_count(), 0);
+        loaded.load_region(&bytes).unwrap();
+        assert_eq!(loaded.edit_count(), 2);
+    }
+}
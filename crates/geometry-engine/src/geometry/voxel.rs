@@ -1,259 +1,859 @@
+use super::blocks::{BlockDefinition, BlockId, BlockRegistry, TerrainBlocks};
+use super::light::{LightChannel, LightStorage, MAX_LIGHT};
 use super::terrain::TerrainParams;
+use super::worldgen::{Column, WorldGenerator};
 
-#[derive(Clone, Copy, Debug, PartialEq)]
-#[repr(u32)]
-pub enum BlockType {
-    Air = 0,
-    Stone = 1,
-    Grass = 2,
-    Dirt = 3,
-    Water = 4,
-    Sand = 5,
-    Wood = 6,
-    Leaves = 7,
+// Block ids of a chunk. Chunks made of a single block type (open air, solid rock)
+// are stored as that one id; all others as palette indices packed into as few bits
+// as the number of distinct block types allows.
+enum BlockStorage {
+    Uniform(BlockId),
+    Paletted(PalettedBlocks),
 }
 
-impl BlockType {
-    pub fn from_u32(value: u32) -> Self {
-        match value {
-            0 => BlockType::Air,
-            1 => BlockType::Stone,
-            2 => BlockType::Grass,
-            3 => BlockType::Dirt,
-            4 => BlockType::Water,
-            5 => BlockType::Sand,
-            6 => BlockType::Wood,
-            7 => BlockType::Leaves,
-            _ => BlockType::Air,
+// Solid blocks above a chunk that volumetric generation looks at to tell how deep its
+// top cells are buried; past the surface layers and cave cut-off the depth is irrelevant
+const SURFACE_LOOKAHEAD: f32 = 8.0;
+
+impl BlockStorage {
+    fn from_dense(blocks: [[[BlockId; 16]; 16]; 16]) -> Self {
+        let mut palette = Vec::new();
+        for &block in blocks.iter().flatten().flatten() {
+            if !palette.contains(&block) {
+                palette.push(block);
+            }
+        }
+        if palette.len() == 1 {
+            return BlockStorage::Uniform(palette[0]);
+        }
+        
+        let mut paletted = PalettedBlocks::new(palette);
+        for (x, plane) in blocks.iter().enumerate() {
+            for (y, column) in plane.iter().enumerate() {
+                for (z, &block) in column.iter().enumerate() {
+                    let palette_index = paletted.palette.iter().position(|&entry| entry == block).unwrap();
+                    paletted.set_index(PalettedBlocks::cell(x, y, z), palette_index);
+                }
+            }
         }
+        BlockStorage::Paletted(paletted)
     }
-
-    pub fn get_color(&self) -> [f32; 3] {
+    
+    fn get(&self, x: usize, y: usize, z: usize) -> BlockId {
+        match self {
+            BlockStorage::Uniform(block) => *block,
+            BlockStorage::Paletted(blocks) => blocks.get(x, y, z),
+        }
+    }
+    
+    fn set(&mut self, x: usize, y: usize, z: usize, block: BlockId) {
+        match self {
+            BlockStorage::Uniform(current) if *current == block => {}
+            BlockStorage::Uniform(current) => {
+                let mut blocks = PalettedBlocks::new(vec![*current, block]);
+                blocks.set_index(PalettedBlocks::cell(x, y, z), 1);
+                *self = BlockStorage::Paletted(blocks);
+            }
+            BlockStorage::Paletted(blocks) => blocks.set(x, y, z, block),
+        }
+    }
+    
+    fn heap_size(&self) -> usize {
         match self {
-            BlockType::Air => [0.0, 0.0, 0.0],
-            BlockType::Stone => [0.5, 0.5, 0.5],
-            BlockType::Grass => [0.0, 1.0, 0.0],
-            BlockType::Dirt => [0.545, 0.271, 0.075],
-            BlockType::Water => [0.0, 0.5, 1.0],
-            BlockType::Sand => [0.957, 0.894, 0.757],
-            BlockType::Wood => [0.396, 0.263, 0.129],
-            BlockType::Leaves => [0.133, 0.545, 0.133],
+            BlockStorage::Uniform(_) => 0,
+            BlockStorage::Paletted(blocks) => blocks.heap_size(),
         }
     }
 }
 
+// Bit-packed palette indices, every cell starting at palette entry 0.
+// Indices never straddle two words, so some high bits of each word may go unused.
+struct PalettedBlocks {
+    palette: Vec<BlockId>,
+    bits: usize,
+    words: Vec<u64>,
+}
+
+impl PalettedBlocks {
+    fn new(palette: Vec<BlockId>) -> Self {
+        let bits = Self::bits_for(palette.len());
+        let per_word = 64 / bits;
+        Self {
+            palette,
+            bits,
+            words: vec![0; 4096usize.div_ceil(per_word)],
+        }
+    }
+    
+    // Smallest index width able to address `len` palette entries
+    fn bits_for(len: usize) -> usize {
+        (usize::BITS - (len.max(2) - 1).leading_zeros()) as usize
+    }
+    
+    fn cell(x: usize, y: usize, z: usize) -> usize {
+        (x * 16 + y) * 16 + z
+    }
+    
+    fn index(&self, cell: usize) -> usize {
+        let per_word = 64 / self.bits;
+        let shift = (cell % per_word) * self.bits;
+        ((self.words[cell / per_word] >> shift) & ((1 << self.bits) - 1)) as usize
+    }
+    
+    fn set_index(&mut self, cell: usize, palette_index: usize) {
+        let per_word = 64 / self.bits;
+        let shift = (cell % per_word) * self.bits;
+        let mask = ((1u64 << self.bits) - 1) << shift;
+        let word = &mut self.words[cell / per_word];
+        *word = (*word & !mask) | ((palette_index as u64) << shift);
+    }
+    
+    fn get(&self, x: usize, y: usize, z: usize) -> BlockId {
+        self.palette[self.index(Self::cell(x, y, z))]
+    }
+    
+    fn set(&mut self, x: usize, y: usize, z: usize, block: BlockId) {
+        let palette_index = match self.palette.iter().position(|&entry| entry == block) {
+            Some(palette_index) => palette_index,
+            None => {
+                self.palette.push(block);
+                if Self::bits_for(self.palette.len()) > self.bits {
+                    self.repack();
+                }
+                self.palette.len() - 1
+            }
+        };
+        self.set_index(Self::cell(x, y, z), palette_index);
+    }
+    
+    // Copies every index into wider words once the palette outgrows the current width
+    fn repack(&mut self) {
+        let mut wider = Self::new(std::mem::take(&mut self.palette));
+        for cell in 0..4096 {
+            wider.set_index(cell, self.index(cell));
+        }
+        *self = wider;
+    }
+    
+    fn heap_size(&self) -> usize {
+        std::mem::size_of_val(self.palette.as_slice()) + std::mem::size_of_val(self.words.as_slice())
+    }
+}
+
 pub struct VoxelChunk {
-    blocks: [[[u32; 16]; 16]; 16],
+    blocks: BlockStorage,
+    // Sky and block light, filled in by the light engine once the chunk is loaded
+    light: LightStorage,
     position: (i32, i32, i32),
+    // Last built mesh, stale while `dirty` is set
+    mesh: ChunkMesh,
+    dirty: bool,
+    // Frame the chunk was last visible in, for least-recently-used eviction
+    last_used: u64,
 }
 
 impl VoxelChunk {
-    pub fn new_with_terrain_params<F, G, H>(
+    /// First generation phase: ground, water and caves of one chunk, shaped by `generator`
+    /// over `columns`, the surface sampled for the chunk's column of chunks.
+    /// Trees and other features spanning chunk borders are written on top afterwards.
+    pub fn new_with_terrain_params<G>(
         chunk_x: i32, 
         chunk_y: i32, 
         chunk_z: i32,
-        params: TerrainParams,
-        terrain_height_fn: F,
-        biome_fn: G,
-        detail_fn: H,
+        columns: &ChunkColumns,
+        params: &TerrainParams,
+        blocks: TerrainBlocks,
+        generator: &G,
     ) -> Self 
     where
-        F: Fn(f32, f32, &TerrainParams) -> f32,
-        G: Fn(f32, f32, &TerrainParams) -> f32,
-        H: Fn(f32, f32, f32, &TerrainParams) -> f32,
+        G: WorldGenerator + ?Sized,
     {
-        let mut blocks = [[[0u32; 16]; 16]; 16];
-        
-        let chunk_world_x = chunk_x as f32 * 16.0;
         let chunk_world_y = chunk_y as f32 * 16.0;
-        let chunk_world_z = chunk_z as f32 * 16.0;
         
-        // Use sea level from params
-        let sea_level = params.sea_level;
+        // Nothing is placed above the ceiling (trees and other features are added
+        // afterwards), water stops at the water level. Chunks below the terrain are not
+        // shortcut the same way because caves can carve into them; those collapse to
+        // uniform storage after filling if nothing was carved.
+        if chunk_world_y >= columns.max_ceiling && chunk_world_y >= columns.max_water_level {
+            return Self::with_blocks((chunk_x, chunk_y, chunk_z), BlockStorage::Uniform(BlockId::AIR));
+        }
         
-        for x in 0..16 {
-            for z in 0..16 {
-                let world_x = chunk_world_x + x as f32;
-                let world_z = chunk_world_z + z as f32;
-                
-                // Get terrain height and biome value
-                let terrain_height = terrain_height_fn(world_x, world_z, &params);
-                let biome = biome_fn(world_x, world_z, &params);
+        let mut cells = [[[BlockId::AIR; 16]; 16]; 16];
+        
+        for (plane, row_columns) in cells.iter_mut().zip(&columns.columns) {
+            for (z, &column) in row_columns.iter().enumerate() {
+                let (world_x, world_z) = (column.x, column.z);
                 
-                // Determine if this is a beach/desert area
-                let is_beach = terrain_height > sea_level - 2.0 && terrain_height < sea_level + 3.0;
-                let is_desert = biome > params.desert_threshold && terrain_height > sea_level;
-                let is_mountain = terrain_height > 15.0;
+                if generator.is_volumetric() {
+                    Self::fill_volumetric_column(plane, z, column, chunk_world_y, params, &blocks, generator);
+                    continue;
+                }
                 
-                for y in 0..16 {
+                for (y, row) in plane.iter_mut().enumerate() {
                     let world_y = chunk_world_y + y as f32;
                     
-                    // Add some 3D noise for caves
-                    let cave_noise = detail_fn(world_x, world_y, world_z, &params);
-                    let is_cave = cave_noise > params.cave_threshold && world_y < terrain_height - 5.0;
+                    // Caves only carve ground well below the surface
+                    let is_cave = world_y < column.height - 5.0
+                        && generator.density(world_x, world_y, world_z, params) > params.cave_threshold;
                     
-                    if is_cave {
-                        blocks[x][y][z] = 0; // Air for caves
-                    } else if world_y < terrain_height - 5.0 {
-                        // Deep underground: always stone
-                        blocks[x][y][z] = 1;
-                    } else if world_y < terrain_height - 2.0 {
-                        // Underground: stone
-                        blocks[x][y][z] = 1;
-                    } else if world_y < terrain_height {
-                        // Near surface
-                        if is_beach || (terrain_height < sea_level + 1.0) {
-                            blocks[x][y][z] = 5; // Sand near water
-                        } else if is_desert {
-                            blocks[x][y][z] = 5; // Sand in desert
-                        } else if is_mountain && world_y > 20.0 {
-                            blocks[x][y][z] = 1; // Stone on mountains
-                        } else {
-                            blocks[x][y][z] = 3; // Dirt
-                        }
-                    } else if world_y < terrain_height + 1.0 {
-                        // Surface layer
-                        if world_y < sea_level && terrain_height < sea_level {
-                            blocks[x][y][z] = 4; // Water
-                        } else if is_beach || is_desert {
-                            blocks[x][y][z] = 5; // Sand
-                        } else if is_mountain && world_y > 25.0 {
-                            blocks[x][y][z] = 1; // Stone on high mountains
-                        } else {
-                            blocks[x][y][z] = 2; // Grass
-                        }
-                    } else if world_y < sea_level {
-                        // Fill with water up to sea level
-                        blocks[x][y][z] = 4;
+                    row[z] = if is_cave {
+                        BlockId::AIR
                     } else {
-                        // Check for trees
-                        if world_y == (terrain_height + 1.0).floor() && 
-                           !is_beach && !is_desert && !is_mountain {
-                            // Tree generation based on density parameter
-                            let tree_hash = ((world_x as i32 * 73 + world_z as i32 * 179) % 100).abs();
-                            if tree_hash < params.tree_density as i32 {
-                                // Place a tree trunk
-                                for tree_y in 0..5 {
-                                    let block_y = y + tree_y;
-                                    if block_y < 16 {
-                                        if tree_y < 4 {
-                                            blocks[x][block_y][z] = 6; // Wood
-                                        } else {
-                                            blocks[x][block_y][z] = 7; // Leaves
-                                        }
-                                    }
-                                }
-                                // Add leaves around the top
-                                if y + 3 < 16 {
-                                    for dx in -1i32..=1 {
-                                        for dz in -1i32..=1 {
-                                            if dx != 0 || dz != 0 {
-                                                let leaf_x = (x as i32 + dx) as usize;
-                                                let leaf_z = (z as i32 + dz) as usize;
-                                                if leaf_x < 16 && leaf_z < 16 && y + 3 < 16 {
-                                                    if blocks[leaf_x][y + 3][leaf_z] == 0 {
-                                                        blocks[leaf_x][y + 3][leaf_z] = 7;
-                                                    }
-                                                }
-                                            }
-                                        }
-                                    }
-                                }
-                            }
-                        } else {
-                            blocks[x][y][z] = 0; // Air
-                        }
-                    }
+                        generator.surface_block(column, world_y, params, &blocks)
+                    };
                 }
             }
         }
         
+        Self::with_blocks((chunk_x, chunk_y, chunk_z), BlockStorage::from_dense(cells))
+    }
+    
+    // Fills a column top-down from the generator's 3D solidity. Each solid block is
+    // surfaced as if the run of solid blocks it belongs to ended in the column's top,
+    // so grass, dirt and sand form on every exposed top, overhangs and islands included.
+    fn fill_volumetric_column<G>(
+        cells: &mut [[BlockId; 16]; 16],
+        z: usize,
+        column: Column,
+        chunk_world_y: f32,
+        params: &TerrainParams,
+        blocks: &TerrainBlocks,
+        generator: &G,
+    ) where
+        G: WorldGenerator + ?Sized,
+    {
+        let is_solid = |world_y: f32| generator.solidity(column.x, world_y, column.z, column.height, params) > 0.0;
+        
+        let mut depth = 0.0;
+        while depth < SURFACE_LOOKAHEAD && is_solid(chunk_world_y + 16.0 + depth) {
+            depth += 1.0;
+        }
+        
+        for (y, row) in cells.iter_mut().enumerate().rev() {
+            let world_y = chunk_world_y + y as f32;
+            row[z] = if is_solid(world_y) {
+                let top = world_y + depth;
+                depth += 1.0;
+                let is_cave = top - world_y > 5.0
+                    && generator.density(column.x, world_y, column.z, params) > params.cave_threshold;
+                if is_cave {
+                    BlockId::AIR
+                } else {
+                    generator.surface_block(Column { height: top, ..column }, world_y, params, blocks)
+                }
+            } else {
+                depth = 0.0;
+                // Open cells get air, or water below the water level
+                generator.surface_block(Column { height: world_y - 1.0, ..column }, world_y, params, blocks)
+            };
+        }
+    }
+    
+    /// Chunk made of exactly `blocks`, indexed `[x][y][z]`, e.g. as read back from a region file
+    pub fn from_blocks(position: (i32, i32, i32), blocks: &[[[BlockId; 16]; 16]; 16]) -> Self {
+        Self::with_blocks(position, BlockStorage::from_dense(*blocks))
+    }
+    
+    fn with_blocks(position: (i32, i32, i32), blocks: BlockStorage) -> Self {
         Self {
             blocks,
-            position: (chunk_x, chunk_y, chunk_z),
+            light: LightStorage::default(),
+            position,
+            mesh: ChunkMesh::default(),
+            dirty: true,
+            last_used: 0,
+        }
+    }
+    
+    pub fn touch(&mut self, frame: u64) {
+        self.last_used = frame;
+    }
+    
+    pub fn last_used(&self) -> u64 {
+        self.last_used
+    }
+    
+    /// Approximate heap and inline size of the chunk including its cached mesh
+    pub fn memory_usage(&self) -> usize {
+        std::mem::size_of::<Self>() + self.blocks.heap_size() + self.light.heap_size() + self.mesh.memory_usage()
+    }
+    
+    /// Flags the cached mesh for rebuilding, e.g. after a neighbouring chunk changed
+    pub fn mark_dirty(&mut self) {
+        self.dirty = true;
+    }
+    
+    pub fn is_dirty(&self) -> bool {
+        self.dirty
+    }
+    
+    pub fn mesh(&self) -> &ChunkMesh {
+        &self.mesh
+    }
+    
+    /// Stores a freshly built mesh and clears the dirty flag.
+    /// Returns whether the mesh differs from the previously cached one.
+    pub fn set_mesh(&mut self, mesh: ChunkMesh) -> bool {
+        self.dirty = false;
+        if self.mesh == mesh {
+            return false;
+        }
+        self.mesh = mesh;
+        true
+    }
+    
+    pub fn get_block(&self, x: usize, y: usize, z: usize) -> BlockId {
+        self.blocks.get(x, y, z)
+    }
+    
+    pub fn light_level(&self, x: usize, y: usize, z: usize, channel: LightChannel) -> u8 {
+        self.light.get(x, y, z, channel)
+    }
+    
+    /// Stores a light level without flagging the chunk; the light engine flags
+    /// every chunk whose shading it changed, including neighbours across the border
+    pub fn set_light_level(&mut self, x: usize, y: usize, z: usize, channel: LightChannel, level: u8) {
+        self.light.set(x, y, z, channel, level);
+    }
+    
+    /// Gives every cell the same light, without flagging the chunk
+    pub fn fill_light(&mut self, sky: u8, block: u8) {
+        self.light = LightStorage::uniform(sky, block);
+    }
+    
+    /// The block filling the whole chunk, if it holds only one type
+    pub fn uniform_block(&self) -> Option<BlockId> {
+        match self.blocks {
+            BlockStorage::Uniform(block) => Some(block),
+            BlockStorage::Paletted(_) => None,
         }
     }
     
-    pub fn get_block(&self, x: usize, y: usize, z: usize) -> BlockType {
-        BlockType::from_u32(self.blocks[x][y][z])
+    /// Replaces a block and flags the chunk for re-meshing
+    pub fn set_block(&mut self, x: usize, y: usize, z: usize, block: BlockId) {
+        self.blocks.set(x, y, z, block);
+        self.dirty = true;
     }
     
-    fn is_face_visible(&self, x: usize, y: usize, z: usize, face: Face) -> bool {
+    fn is_face_visible(
+        &self,
+        x: usize,
+        y: usize,
+        z: usize,
+        face: Face,
+        neighbours: &ChunkNeighbours,
+        registry: &BlockRegistry,
+    ) -> bool {
         let block = self.get_block(x, y, z);
-        if block == BlockType::Air {
+        if registry.get(block).is_air() {
             return false;
         }
         
-        match face {
-            Face::Top => y == 15 || self.get_block(x, y + 1, z) == BlockType::Air,
-            Face::Bottom => y == 0 || self.get_block(x, y - 1, z) == BlockType::Air,
-            Face::Left => x == 0 || self.get_block(x - 1, y, z) == BlockType::Air,
-            Face::Right => x == 15 || self.get_block(x + 1, y, z) == BlockType::Air,
-            Face::Front => z == 15 || self.get_block(x, y, z + 1) == BlockType::Air,
-            Face::Back => z == 0 || self.get_block(x, y, z - 1) == BlockType::Air,
-        }
-    }
-    
-    pub fn generate_mesh(&self) -> (Vec<f32>, Vec<u32>, Vec<f32>, Vec<f32>) {
-        let mut vertices = Vec::new();
-        let mut indices = Vec::new();
-        let mut normals = Vec::new();
-        let mut colors = Vec::new();
-        let mut vertex_count = 0u32;
+        let (dx, dy, dz) = face.normal();
+        let adjacent = self.block_or_neighbour([x as i32 + dx, y as i32 + dy, z as i32 + dz], neighbours);
         
-        let block_size = 1.0f32;
-        let chunk_offset_x = self.position.0 as f32 * 16.0;
-        let chunk_offset_y = self.position.1 as f32 * 16.0;
-        let chunk_offset_z = self.position.2 as f32 * 16.0;
+        // Borders facing a chunk that is not loaded yet stay exposed. Faces between two
+        // blocks of the same transparent type (a body of water) are hidden.
+        adjacent.is_none_or(|adjacent| adjacent != block && registry.get(adjacent).transparent)
+    }
+    
+    // Block at a position relative to this chunk that may lie up to one block outside it,
+    // `None` when that falls in a neighbouring chunk which is not loaded
+    fn block_or_neighbour(&self, pos: [i32; 3], neighbours: &ChunkNeighbours) -> Option<BlockId> {
+        if pos.iter().all(|coordinate| (0..16).contains(coordinate)) {
+            Some(self.get_block(pos[0] as usize, pos[1] as usize, pos[2] as usize))
+        } else {
+            neighbours.block(pos)
+        }
+    }
+    
+    // Light at a position relative to this chunk, with the same reach as `block_or_neighbour`
+    fn light_or_neighbour(&self, pos: [i32; 3], channel: LightChannel, neighbours: &ChunkNeighbours) -> Option<u8> {
+        if pos.iter().all(|coordinate| (0..16).contains(coordinate)) {
+            Some(self.light_level(pos[0] as usize, pos[1] as usize, pos[2] as usize, channel))
+        } else {
+            neighbours.light(pos, channel)
+        }
+    }
+    
+    // Ambient occlusion and smoothed light of the four corners of a block face, in the
+    // vertex order of `get_face_geometry`. Each corner looks at the two blocks beside it
+    // and the one diagonal to it in the layer in front of the face.
+    fn face_shading(
+        &self,
+        x: usize,
+        y: usize,
+        z: usize,
+        face: Face,
+        neighbours: &ChunkNeighbours,
+        registry: &BlockRegistry,
+    ) -> FaceShading {
+        let d = face.axis();
+        let u = (d + 1) % 3;
+        let v = (d + 2) % 3;
+        let (nx, ny, nz) = face.normal();
+        let front = [x as i32 + nx, y as i32 + ny, z as i32 + nz];
+        let offset = |du: i32, dv: i32| {
+            let mut pos = front;
+            pos[u] += du;
+            pos[v] += dv;
+            pos
+        };
+        let occludes = |pos: [i32; 3]| {
+            self.block_or_neighbour(pos, neighbours)
+                .is_some_and(|block| !registry.get(block).transparent)
+        };
+        // Light of the cell the face looks into; a chunk that is not loaded yet counts as open sky
+        let front_light = |channel: LightChannel| {
+            self.light_or_neighbour(front, channel, neighbours).unwrap_or(match channel {
+                LightChannel::Sky => MAX_LIGHT,
+                LightChannel::Block => 0,
+            })
+        };
+        
+        // Corners of the unit face tell which way each vertex leans along u and v
+        let (corners, _) = get_face_geometry(face, [0.0; 3], [1.0; 3]);
+        let mut shading = FaceShading::default();
+        for (corner_index, corner) in corners.chunks_exact(3).enumerate() {
+            let du = if corner[u] > 0.5 { 1 } else { -1 };
+            let dv = if corner[v] > 0.5 { 1 } else { -1 };
+            let side_u = occludes(offset(du, 0));
+            let side_v = occludes(offset(0, dv));
+            let diagonal = !(side_u && side_v) && occludes(offset(du, dv));
+            shading.ao[corner_index] = if side_u && side_v {
+                0
+            } else {
+                3 - side_u as u8 - side_v as u8 - diagonal as u8
+            };
+            
+            // Average over the front cell and whichever cells around the corner let light
+            // through; the diagonal is cut off when both sides are blocked
+            let around = [
+                (offset(du, 0), !side_u),
+                (offset(0, dv), !side_v),
+                (offset(du, dv), !(diagonal || (side_u && side_v))),
+            ];
+            for (channel, level) in [(LightChannel::Sky, &mut shading.sky), (LightChannel::Block, &mut shading.block)] {
+                let mut total = front_light(channel) as u32;
+                let mut count = 1;
+                for &(pos, open) in &around {
+                    if let Some(light) = self.light_or_neighbour(pos, channel, neighbours).filter(|_| open) {
+                        total += light as u32;
+                        count += 1;
+                    }
+                }
+                level[corner_index] = (total * 4 / count) as u8;
+            }
+        }
+        shading
+    }
+    
+    /// Builds the chunk mesh, with texture coordinates when an `atlas` is given
+    pub fn generate_mesh(
+        &self,
+        mode: MeshingMode,
+        neighbours: &ChunkNeighbours,
+        registry: &BlockRegistry,
+        atlas: Option<&TextureAtlas>,
+    ) -> ChunkMesh {
+        if self.uniform_block().is_some_and(|block| registry.get(block).is_air()) {
+            return ChunkMesh::default();
+        }
+        match mode {
+            MeshingMode::Naive => self.generate_naive_mesh(neighbours, registry, atlas),
+            MeshingMode::Greedy => self.generate_greedy_mesh(neighbours, registry, atlas),
+        }
+    }
+    
+    fn chunk_offset(&self) -> [f32; 3] {
+        [
+            self.position.0 as f32 * 16.0,
+            self.position.1 as f32 * 16.0,
+            self.position.2 as f32 * 16.0,
+        ]
+    }
+    
+    // One quad per visible block face
+    fn generate_naive_mesh(
+        &self,
+        neighbours: &ChunkNeighbours,
+        registry: &BlockRegistry,
+        atlas: Option<&TextureAtlas>,
+    ) -> ChunkMesh {
+        let mut mesh = ChunkMesh::default();
+        let offset = self.chunk_offset();
         
         for x in 0..16 {
             for y in 0..16 {
                 for z in 0..16 {
-                    let block = self.get_block(x, y, z);
-                    if block == BlockType::Air {
+                    let block = registry.get(self.get_block(x, y, z));
+                    if block.is_air() {
                         continue;
                     }
                     
-                    let color = block.get_color();
-                    let pos_x = chunk_offset_x + x as f32 * block_size;
-                    let pos_y = chunk_offset_y + y as f32 * block_size;
-                    let pos_z = chunk_offset_z + z as f32 * block_size;
+                    let origin = [
+                        offset[0] + x as f32,
+                        offset[1] + y as f32,
+                        offset[2] + z as f32,
+                    ];
                     
                     // Check each face for visibility and add geometry
-                    for face in &[Face::Top, Face::Bottom, Face::Left, Face::Right, Face::Front, Face::Back] {
-                        if self.is_face_visible(x, y, z, *face) {
-                            let (face_vertices, face_normals) = get_face_geometry(*face, pos_x, pos_y, pos_z, block_size);
-                            
-                            // Add vertices
-                            vertices.extend_from_slice(&face_vertices);
-                            normals.extend_from_slice(&face_normals);
-                            
-                            // Add colors (4 vertices per face)
-                            for _ in 0..4 {
-                                colors.extend_from_slice(&color);
+                    for face in Face::ALL {
+                        if self.is_face_visible(x, y, z, face, neighbours, registry) {
+                            let shading = self.face_shading(x, y, z, face, neighbours, registry);
+                            mesh.layer_mut(block.transparent).push_quad(face, origin, [1.0, 1.0, 1.0], block, shading, atlas);
+                        }
+                    }
+                }
+            }
+        }
+        
+        mesh
+    }
+    
+    // Merges coplanar visible faces of the same block type and corner shading into
+    // maximal rectangles. Covers exactly the same surface as the naive mesher with far fewer quads.
+    fn generate_greedy_mesh(
+        &self,
+        neighbours: &ChunkNeighbours,
+        registry: &BlockRegistry,
+        atlas: Option<&TextureAtlas>,
+    ) -> ChunkMesh {
+        let mut mesh = ChunkMesh::default();
+        let offset = self.chunk_offset();
+        
+        for face in Face::ALL {
+            // Slice along the face normal axis, sweeping the two in-plane axes
+            let d = face.axis();
+            let u = (d + 1) % 3;
+            let v = (d + 2) % 3;
+            
+            for slice in 0..16 {
+                let mut mask = [[None; 16]; 16];
+                for (i, row) in mask.iter_mut().enumerate() {
+                    for (j, cell) in row.iter_mut().enumerate() {
+                        let mut pos = [0usize; 3];
+                        pos[d] = slice;
+                        pos[u] = i;
+                        pos[v] = j;
+                        if self.is_face_visible(pos[0], pos[1], pos[2], face, neighbours, registry) {
+                            let shading = self.face_shading(pos[0], pos[1], pos[2], face, neighbours, registry);
+                            *cell = Some((self.get_block(pos[0], pos[1], pos[2]), shading));
+                        }
+                    }
+                }
+                
+                for j in 0..16 {
+                    let mut i = 0;
+                    while i < 16 {
+                        let Some((block, shading)) = mask[i][j] else {
+                            i += 1;
+                            continue;
+                        };
+                        
+                        // Grow along u, then along v while the whole row matches
+                        let mut width = 1;
+                        while i + width < 16 && mask[i + width][j] == Some((block, shading)) {
+                            width += 1;
+                        }
+                        let mut height = 1;
+                        while j + height < 16
+                            && (i..i + width).all(|k| mask[k][j + height] == Some((block, shading)))
+                        {
+                            height += 1;
+                        }
+                        
+                        for row in mask.iter_mut().skip(i).take(width) {
+                            for cell in row.iter_mut().skip(j).take(height) {
+                                *cell = None;
                             }
-                            
-                            // Add indices (2 triangles per face)
-                            indices.push(vertex_count);
-                            indices.push(vertex_count + 1);
-                            indices.push(vertex_count + 2);
-                            indices.push(vertex_count);
-                            indices.push(vertex_count + 2);
-                            indices.push(vertex_count + 3);
-                            
-                            vertex_count += 4;
                         }
+                        
+                        let mut origin = offset;
+                        origin[d] += slice as f32;
+                        origin[u] += i as f32;
+                        origin[v] += j as f32;
+                        let mut size = [1.0f32; 3];
+                        size[u] = width as f32;
+                        size[v] = height as f32;
+                        let block = registry.get(block);
+                        mesh.layer_mut(block.transparent).push_quad(face, origin, size, block, shading, atlas);
+                        
+                        i += width;
                     }
                 }
             }
         }
         
-        (vertices, indices, normals, colors)
+        mesh
+    }
+}
+
+/// Surface of the 16x16 world columns under a column of chunks. Sampled once and shared
+/// by every chunk stacked on it, since the heightmap and water levels are the costly part.
+pub struct ChunkColumns {
+    columns: [[Column; 16]; 16],
+    // Highest ceiling and water level over all columns, to recognise chunks above the terrain
+    max_ceiling: f32,
+    max_water_level: f32,
+}
+
+impl ChunkColumns {
+    pub fn sample<G>(chunk_x: i32, chunk_z: i32, params: &TerrainParams, generator: &G) -> Self
+    where
+        G: WorldGenerator + ?Sized,
+    {
+        let columns: [[Column; 16]; 16] = std::array::from_fn(|x| {
+            std::array::from_fn(|z| {
+                let world_x = chunk_x as f32 * 16.0 + x as f32;
+                let world_z = chunk_z as f32 * 16.0 + z as f32;
+                Column {
+                    x: world_x,
+                    z: world_z,
+                    height: generator.height(world_x, world_z, params),
+                    biome: generator.biome(world_x, world_z, params),
+                    water_level: generator.water_level(world_x, world_z, params),
+                }
+            })
+        });
+        let mut max_ceiling = f32::MIN;
+        let mut max_water_level = f32::MIN;
+        for column in columns.iter().flatten() {
+            max_ceiling = max_ceiling.max(generator.ceiling(column.height, params));
+            max_water_level = max_water_level.max(column.water_level);
+        }
+        Self {
+            columns,
+            max_ceiling,
+            max_water_level,
+        }
+    }
+}
+
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub enum MeshingMode {
+    /// One quad per visible block face
+    #[default]
+    Naive,
+    /// Coplanar faces of the same block type merged into rectangles
+    Greedy,
+}
+
+/// Grid of equally sized tiles in one texture, numbered row by row from the top left
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct TextureAtlas {
+    columns: u32,
+    rows: u32,
+}
+
+impl TextureAtlas {
+    pub fn new(columns: u32, rows: u32) -> Self {
+        Self {
+            columns: columns.max(1),
+            rows: rows.max(1),
+        }
+    }
+    
+    // Lower left corner of a tile in UV space (v pointing up, as in WebGL),
+    // `None` for tiles outside the grid
+    fn tile_origin(&self, tile: u32) -> Option<[f32; 2]> {
+        if tile >= self.columns * self.rows {
+            return None;
+        }
+        let column = tile % self.columns;
+        let row = tile / self.columns;
+        Some([
+            column as f32 / self.columns as f32,
+            1.0 - (row + 1) as f32 / self.rows as f32,
+        ])
+    }
+}
+
+/// The 26 chunks around a chunk, used to cull faces and shade corners on its border
+#[derive(Default)]
+pub struct ChunkNeighbours<'a> {
+    // 3x3x3 grid indexed by offset + 1 on each axis; the centre slot stays empty
+    chunks: [Option<&'a VoxelChunk>; 27],
+}
+
+impl<'a> ChunkNeighbours<'a> {
+    pub fn new<F>(pos: (i32, i32, i32), mut lookup: F) -> Self
+    where
+        F: FnMut((i32, i32, i32)) -> Option<&'a VoxelChunk>,
+    {
+        let mut chunks = [None; 27];
+        for dx in -1..=1 {
+            for dy in -1..=1 {
+                for dz in -1..=1 {
+                    if (dx, dy, dz) != (0, 0, 0) {
+                        chunks[Self::slot([dx, dy, dz])] = lookup((pos.0 + dx, pos.1 + dy, pos.2 + dz));
+                    }
+                }
+            }
+        }
+        Self { chunks }
+    }
+    
+    fn slot(offset: [i32; 3]) -> usize {
+        ((offset[0] + 1) * 9 + (offset[1] + 1) * 3 + offset[2] + 1) as usize
+    }
+    
+    // Block at a position relative to the centre chunk that lies in one of the neighbours
+    fn block(&self, pos: [i32; 3]) -> Option<BlockId> {
+        let chunk = self.chunks[Self::slot(pos.map(|coordinate| coordinate.div_euclid(16)))]?;
+        let [x, y, z] = pos.map(|coordinate| coordinate.rem_euclid(16) as usize);
+        Some(chunk.get_block(x, y, z))
+    }
+    
+    fn light(&self, pos: [i32; 3], channel: LightChannel) -> Option<u8> {
+        let chunk = self.chunks[Self::slot(pos.map(|coordinate| coordinate.div_euclid(16)))]?;
+        let [x, y, z] = pos.map(|coordinate| coordinate.rem_euclid(16) as usize);
+        Some(chunk.light_level(x, y, z, channel))
+    }
+}
+
+// Colour multiplier per ambient occlusion level, from fully occluded to open
+const AO_BRIGHTNESS: [f32; 4] = [0.5, 0.65, 0.8, 1.0];
+
+// Per-corner shading of a block face. Ambient occlusion runs from 0 (darkest) to 3 (open);
+// light levels are averaged over up to four cells and kept in quarter steps (0..=60) so
+// equal corners still compare exactly when merging.
+#[derive(Clone, Copy, Default, PartialEq)]
+struct FaceShading {
+    ao: [u8; 4],
+    sky: [u8; 4],
+    block: [u8; 4],
+}
+
+/// Geometry of a chunk, split so transparent blocks can be blended after opaque terrain
+#[derive(Clone, Default, PartialEq)]
+pub struct ChunkMesh {
+    pub opaque: MeshBuffers,
+    pub transparent: MeshBuffers,
+}
+
+impl ChunkMesh {
+    pub fn is_empty(&self) -> bool {
+        self.opaque.is_empty() && self.transparent.is_empty()
+    }
+    
+    pub fn memory_usage(&self) -> usize {
+        self.opaque.memory_usage() + self.transparent.memory_usage()
+    }
+    
+    fn layer_mut(&mut self, transparent: bool) -> &mut MeshBuffers {
+        if transparent {
+            &mut self.transparent
+        } else {
+            &mut self.opaque
+        }
+    }
+    
+    /// Appends another mesh, offsetting its indices past the existing vertices
+    pub fn append(&mut self, other: &ChunkMesh) {
+        self.opaque.append(&other.opaque);
+        self.transparent.append(&other.transparent);
     }
 }
 
-#[derive(Clone, Copy, Debug)]
-enum Face {
+/// Indexed triangle buffers of one render pass
+#[derive(Clone, Default, PartialEq)]
+pub struct MeshBuffers {
+    pub vertices: Vec<f32>,
+    pub indices: Vec<u32>,
+    pub normals: Vec<f32>,
+    pub colors: Vec<f32>,
+    /// Sky and block light per vertex, two values from 0 (dark) to 1 (full light)
+    pub light: Vec<f32>,
+    /// Position within the face in blocks, two values per vertex. Counts past 1 on
+    /// merged quads, so the texture repeats once per block. Empty without an atlas.
+    pub uvs: Vec<f32>,
+    /// Lower left corner of the vertex's atlas tile, two values per vertex; the sampled
+    /// UV is this plus the fractional part of `uvs` times the tile size. Negative on
+    /// faces of untextured blocks. Empty without an atlas.
+    pub uv_tiles: Vec<f32>,
+}
+
+impl MeshBuffers {
+    pub fn is_empty(&self) -> bool {
+        self.indices.is_empty()
+    }
+    
+    pub fn memory_usage(&self) -> usize {
+        let floats = self.vertices.capacity()
+            + self.normals.capacity()
+            + self.colors.capacity()
+            + self.light.capacity()
+            + self.uvs.capacity()
+            + self.uv_tiles.capacity();
+        floats * std::mem::size_of::<f32>()
+            + self.indices.capacity() * std::mem::size_of::<u32>()
+    }
+    
+    fn append(&mut self, other: &MeshBuffers) {
+        let vertex_offset = (self.vertices.len() / 3) as u32;
+        self.vertices.extend_from_slice(&other.vertices);
+        self.indices.extend(other.indices.iter().map(|index| index + vertex_offset));
+        self.normals.extend_from_slice(&other.normals);
+        self.colors.extend_from_slice(&other.colors);
+        self.light.extend_from_slice(&other.light);
+        self.uvs.extend_from_slice(&other.uvs);
+        self.uv_tiles.extend_from_slice(&other.uv_tiles);
+    }
+    
+    /// Adds the `face` side of the box at `origin` with extents `size`, darkening each
+    /// vertex colour by its ambient occlusion level and recording its light. Textured
+    /// faces get a white base colour so the atlas shows through untinted.
+    fn push_quad(
+        &mut self,
+        face: Face,
+        origin: [f32; 3],
+        size: [f32; 3],
+        block: &BlockDefinition,
+        shading: FaceShading,
+        atlas: Option<&TextureAtlas>,
+    ) {
+        let vertex_count = (self.vertices.len() / 3) as u32;
+        let (face_vertices, face_normals) = get_face_geometry(face, origin, size);
+        let tile = atlas.and_then(|atlas| atlas.tile_origin(block.textures.tile(face)?));
+        let color = if tile.is_some() { [1.0; 3] } else { block.color };
+        
+        if atlas.is_some() {
+            let tile = tile.unwrap_or([-1.0, -1.0]);
+            for vertex in face_vertices.chunks_exact(3) {
+                let local = [vertex[0] - origin[0], vertex[1] - origin[1], vertex[2] - origin[2]];
+                self.uvs.extend_from_slice(&face_uv(face, local, size));
+                self.uv_tiles.extend_from_slice(&tile);
+            }
+        }
+        
+        // Add vertices
+        self.vertices.extend_from_slice(&face_vertices);
+        self.normals.extend_from_slice(&face_normals);
+        
+        // Add colors (4 vertices per face)
+        for corner in 0..4 {
+            let brightness = AO_BRIGHTNESS[shading.ao[corner] as usize];
+            self.colors.extend_from_slice(&color.map(|channel| channel * brightness));
+            let max_quarters = (MAX_LIGHT * 4) as f32;
+            self.light.push(shading.sky[corner] as f32 / max_quarters);
+            self.light.push(shading.block[corner] as f32 / max_quarters);
+        }
+        
+        // Add indices (2 triangles per face), split along the diagonal whose corners are
+        // more alike so the occlusion gradient does not depend on the quad's orientation
+        let ao = shading.ao;
+        let triangles = if ao[0] + ao[2] < ao[1] + ao[3] {
+            [0, 1, 3, 1, 2, 3]
+        } else {
+            [0, 1, 2, 0, 2, 3]
+        };
+        self.indices.extend(triangles.map(|corner| vertex_count + corner));
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Face {
     Top,
     Bottom,
     Left,
@@ -262,43 +862,84 @@ enum Face {
     Back,
 }
 
-fn get_face_geometry(face: Face, x: f32, y: f32, z: f32, size: f32) -> (Vec<f32>, Vec<f32>) {
+impl Face {
+    pub const ALL: [Face; 6] = [Face::Top, Face::Bottom, Face::Left, Face::Right, Face::Front, Face::Back];
+    
+    // Index of the axis the face normal points along (0 = x, 1 = y, 2 = z)
+    pub fn axis(&self) -> usize {
+        match self {
+            Face::Left | Face::Right => 0,
+            Face::Top | Face::Bottom => 1,
+            Face::Front | Face::Back => 2,
+        }
+    }
+    
+    /// Unit normal of the face, also the offset to the adjacent block or chunk
+    pub fn normal(&self) -> (i32, i32, i32) {
+        match self {
+            Face::Top => (0, 1, 0),
+            Face::Bottom => (0, -1, 0),
+            Face::Left => (-1, 0, 0),
+            Face::Right => (1, 0, 0),
+            Face::Front => (0, 0, 1),
+            Face::Back => (0, 0, -1),
+        }
+    }
+}
+
+// Texture coordinate of a point on a face, `local` to the quad's origin. Side faces keep v
+// pointing up and u running to the right as seen from outside, so textures are not mirrored.
+fn face_uv(face: Face, local: [f32; 3], size: [f32; 3]) -> [f32; 2] {
+    let [x, y, z] = local;
+    match face {
+        Face::Top => [x, z],
+        Face::Bottom => [x, size[2] - z],
+        Face::Left => [z, y],
+        Face::Right => [size[2] - z, y],
+        Face::Front => [x, y],
+        Face::Back => [size[0] - x, y],
+    }
+}
+
+fn get_face_geometry(face: Face, origin: [f32; 3], size: [f32; 3]) -> (Vec<f32>, Vec<f32>) {
+    let [x, y, z] = origin;
+    let [sx, sy, sz] = size;
     let vertices = match face {
         Face::Top => vec![
-            x, y + size, z,
-            x + size, y + size, z,
-            x + size, y + size, z + size,
-            x, y + size, z + size,
+            x, y + sy, z,
+            x + sx, y + sy, z,
+            x + sx, y + sy, z + sz,
+            x, y + sy, z + sz,
         ],
         Face::Bottom => vec![
-            x, y, z + size,
-            x + size, y, z + size,
-            x + size, y, z,
+            x, y, z + sz,
+            x + sx, y, z + sz,
+            x + sx, y, z,
             x, y, z,
         ],
         Face::Left => vec![
             x, y, z,
-            x, y, z + size,
-            x, y + size, z + size,
-            x, y + size, z,
+            x, y, z + sz,
+            x, y + sy, z + sz,
+            x, y + sy, z,
         ],
         Face::Right => vec![
-            x + size, y, z + size,
-            x + size, y, z,
-            x + size, y + size, z,
-            x + size, y + size, z + size,
+            x + sx, y, z + sz,
+            x + sx, y, z,
+            x + sx, y + sy, z,
+            x + sx, y + sy, z + sz,
         ],
         Face::Front => vec![
-            x, y, z + size,
-            x + size, y, z + size,
-            x + size, y + size, z + size,
-            x, y + size, z + size,
+            x, y, z + sz,
+            x + sx, y, z + sz,
+            x + sx, y + sy, z + sz,
+            x, y + sy, z + sz,
         ],
         Face::Back => vec![
-            x + size, y, z,
+            x + sx, y, z,
             x, y, z,
-            x, y + size, z,
-            x + size, y + size, z,
+            x, y + sy, z,
+            x + sx, y + sy, z,
         ],
     };
     
@@ -317,4 +958,114 @@ fn get_face_geometry(face: Face, x: f32, y: f32, z: f32, size: f32) -> (Vec<f32>
     }
     
     (vertices, normals)
-}
\ No newline at end of file
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::{BTreeSet, HashMap};
+    use super::*;
+    use crate::geometry::worldgen::StandardGenerator;
+
+    // Block faces of one layer as (normal, unit cell, corner colours and light), with
+    // every quad split into the unit faces it covers
+    fn unit_faces(buffers: &MeshBuffers) -> BTreeSet<([i32; 3], [i32; 3], Vec<u32>)> {
+        let mut faces = BTreeSet::new();
+        for quad in 0..buffers.vertices.len() / 12 {
+            let corners = &buffers.vertices[quad * 12..quad * 12 + 12];
+            let normal = [0, 1, 2].map(|axis| buffers.normals[quad * 12 + axis] as i32);
+            let min = [0, 1, 2].map(|axis| corners.iter().skip(axis).step_by(3).copied().fold(f32::MAX, f32::min) as i32);
+            let max = [0, 1, 2].map(|axis| corners.iter().skip(axis).step_by(3).copied().fold(f32::MIN, f32::max) as i32);
+            let shading: Vec<u32> = buffers.colors[quad * 12..quad * 12 + 12].iter()
+                .chain(&buffers.light[quad * 8..quad * 8 + 8])
+                .map(|value| value.to_bits())
+                .collect();
+            for x in min[0]..max[0].max(min[0] + 1) {
+                for y in min[1]..max[1].max(min[1] + 1) {
+                    for z in min[2]..max[2].max(min[2] + 1) {
+                        assert!(faces.insert((normal, [x, y, z], shading.clone())), "overlapping faces");
+                    }
+                }
+            }
+        }
+        faces
+    }
+
+    #[test]
+    fn palette_repacks_without_losing_blocks() {
+        let mut storage = BlockStorage::Uniform(BlockId::AIR);
+        let mut expected = [BlockId::AIR; 4096];
+        let mut widths = vec![];
+        // Small LCG so the cells are scattered but the test is repeatable
+        let mut state = 12345u32;
+        for write in 0..600u16 {
+            state = state.wrapping_mul(1_103_515_245).wrapping_add(12_345);
+            let cell = (state >> 8) as usize % 4096;
+            // A new id every 50 writes, up to 12 distinct ones next to air
+            let block = BlockId(1 + write / 50);
+            let (x, y, z) = (cell / 256, cell / 16 % 16, cell % 16);
+            storage.set(x, y, z, block);
+            expected[PalettedBlocks::cell(x, y, z)] = block;
+
+            let BlockStorage::Paletted(blocks) = &storage else {
+                panic!("storage stayed uniform after writing {:?}", block);
+            };
+            if widths.last() != Some(&blocks.bits) {
+                widths.push(blocks.bits);
+            }
+            for x in 0..16 {
+                for y in 0..16 {
+                    for z in 0..16 {
+                        assert_eq!(storage.get(x, y, z), expected[PalettedBlocks::cell(x, y, z)]);
+                    }
+                }
+            }
+        }
+        assert_eq!(widths, [1, 2, 3, 4]);
+
+        // Writing the block a uniform chunk is made of keeps it uniform
+        let mut storage = BlockStorage::Uniform(BlockId(3));
+        storage.set(1, 2, 3, BlockId(3));
+        assert!(matches!(storage, BlockStorage::Uniform(BlockId(3))));
+    }
+
+    fn quad_count(buffers: &MeshBuffers) -> usize {
+        buffers.indices.len() / 6
+    }
+
+    #[test]
+    fn greedy_mesh_covers_the_naive_faces_with_fewer_quads() {
+        let params = TerrainParams::default();
+        let registry = BlockRegistry::default();
+        let mut chunks = HashMap::new();
+        // Naive and greedy quads of the opaque and transparent layers over every chunk
+        let mut quads = [[0; 2]; 2];
+        for centre in [(0, 0, 0), (0, -1, 0), (5, 0, -3), (5, -1, -3), (-7, 1, 12), (-7, -2, 12)] {
+            for dx in -1..=1 {
+                for dy in -1..=1 {
+                    for dz in -1..=1 {
+                        let pos = (centre.0 + dx, centre.1 + dy, centre.2 + dz);
+                        chunks.entry(pos).or_insert_with(|| {
+                            let columns = ChunkColumns::sample(pos.0, pos.2, &params, &StandardGenerator);
+                            VoxelChunk::new_with_terrain_params(pos.0, pos.1, pos.2, &columns, &params, registry.terrain_blocks(), &StandardGenerator)
+                        });
+                    }
+                }
+            }
+
+            let chunk = &chunks[&centre];
+            let neighbours = ChunkNeighbours::new(centre, |pos| chunks.get(&pos));
+            let naive = chunk.generate_mesh(MeshingMode::Naive, &neighbours, &registry, None);
+            let greedy = chunk.generate_mesh(MeshingMode::Greedy, &neighbours, &registry, None);
+            let layers = [(&naive.opaque, &greedy.opaque), (&naive.transparent, &greedy.transparent)];
+            for (layer, (naive, greedy)) in layers.into_iter().enumerate() {
+                assert_eq!(unit_faces(naive), unit_faces(greedy), "chunk {:?}", centre);
+                quads[layer][0] += quad_count(naive);
+                quads[layer][1] += quad_count(greedy);
+            }
+        }
+        for [naive, greedy] in quads {
+            assert!(naive > 0);
+            assert!(greedy < naive, "{} greedy quads for {} naive ones", greedy, naive);
+        }
+    }
+}
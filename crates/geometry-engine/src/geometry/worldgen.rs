@@ -0,0 +1,380 @@
+// Pluggable world generation. A `WorldGenerator` supplies the column functions, surface
+// rules and decorations chunk generation is driven by. Every method defaults to the
+// standard terrain, so a generator only overrides what it changes. Generators are picked
+// by name from a `WorldGenerators` registry.
+use std::collections::HashMap;
+use super::blocks::{BlockId, TerrainBlocks};
+use super::climate::{Biome, Climate};
+use super::features::{self, PlacedFeature};
+use super::hydrology;
+use super::noise::{self, Fbm};
+use super::terrain::TerrainParams;
+
+type ChunkPos = (i32, i32, i32);
+
+/// Generator used until another one is picked
+pub const DEFAULT_GENERATOR: &str = "default";
+
+/// Vertical block range loaded unless a generator suggests another, `max` exclusive
+pub const DEFAULT_HEIGHT_RANGE: (i32, i32) = (-64, 128);
+
+/// Values of one world column that surface rules are evaluated against
+#[derive(Clone, Copy, Debug)]
+pub struct Column {
+    pub x: f32,
+    pub z: f32,
+    /// Surface height from `WorldGenerator::height`
+    pub height: f32,
+    /// Biome from `WorldGenerator::biome`
+    pub biome: Biome,
+    /// Water surface from `WorldGenerator::water_level`
+    pub water_level: f32,
+}
+
+/// Shape and surface of the terrain. Chunks entirely above the `ceiling` and the
+/// `water_level` of all their columns are assumed to be empty before decorations.
+pub trait WorldGenerator {
+    /// Land surface of the world column at (x, z) before rivers and lakes are carved
+    /// into it: the base relief reshaped by the blended height modifiers of the
+    /// surrounding biomes
+    fn relief(&self, x: f32, z: f32, params: &TerrainParams) -> f32 {
+        let seed = params.seed_hash();
+        let fbm = params.fbm();
+        let mut height = 0.0;
+
+        // Large scale terrain features (mountains and valleys)
+        height += fbm.sample2d(seed, x * 0.003, z * 0.003) * params.mountain_scale;
+
+        // Medium scale hills
+        height += fbm.sample2d(seed.wrapping_add(1), x * 0.01, z * 0.01) * params.hills_scale;
+
+        // Small scale bumps (roughness)
+        height += fbm.sample2d(seed.wrapping_add(2), x * 0.05, z * 0.05) * params.roughness;
+
+        // Create some plateaus and cliffs
+        let plateau = noise::perlin2d(seed.wrapping_add(3), x * 0.002, z * 0.002);
+        if plateau > 0.3 {
+            height += 20.0;
+        }
+
+        let (scale, offset) = Climate::at(x, z, params).height_modifiers();
+        height * scale + offset
+    }
+
+    /// Surface height of the world column at (x, z), with rivers and lakes carved in
+    fn height(&self, x: f32, z: f32, params: &TerrainParams) -> f32 {
+        hydrology::carve(x, z, params, |x, z| self.relief(x, z, params))
+    }
+
+    /// Height open cells of the column at (x, z) fill with water up to
+    fn water_level(&self, x: f32, z: f32, params: &TerrainParams) -> f32 {
+        hydrology::water_level(x, z, params, |x, z| self.relief(x, z, params))
+    }
+
+    /// Biome of a column, picked by its climate
+    fn biome(&self, x: f32, z: f32, params: &TerrainParams) -> Biome {
+        Climate::at(x, z, params).biome()
+    }
+
+    /// Cave noise at a point. Ground more than 5 blocks below the surface is carved out
+    /// wherever it exceeds `cave_threshold`.
+    fn density(&self, x: f32, y: f32, z: f32, params: &TerrainParams) -> f32 {
+        // Scaled to the 1.5 peak that cave_threshold was tuned for
+        let caves = Fbm { octaves: 2, lacunarity: 2.0, gain: 0.5 };
+        caves.sample3d(params.seed_hash(), x * 0.05, y * 0.05, z * 0.05) * 1.5
+    }
+
+    /// Vertical block range, `max` exclusive, that holds the whole terrain. Applied as
+    /// the world height range when the generator is picked.
+    fn height_range(&self) -> (i32, i32) {
+        DEFAULT_HEIGHT_RANGE
+    }
+
+    /// Whether solidity comes from `solidity` in 3D rather than from the heightmap
+    fn is_volumetric(&self) -> bool {
+        false
+    }
+
+    /// For volumetric generators, solid where positive. `height` is the column's
+    /// `height`, e.g. to fade from solid ground below to open sky above.
+    fn solidity(&self, _x: f32, y: f32, _z: f32, height: f32, _params: &TerrainParams) -> f32 {
+        height + 1.0 - y
+    }
+
+    /// Height from which a column of `height` is certainly empty, water aside
+    fn ceiling(&self, height: f32, _params: &TerrainParams) -> f32 {
+        height + 1.0
+    }
+
+    /// Block at height `y` of a column where no cave was carved. Volumetric generators
+    /// get the top of the solid run containing `y` as `column.height`.
+    fn surface_block(&self, column: Column, y: f32, params: &TerrainParams, blocks: &TerrainBlocks) -> BlockId {
+        let terrain_height = column.height;
+        let sea_level = params.sea_level;
+        let water_level = column.water_level;
+        let biome = column.biome;
+
+        // Determine if this is a beach
+        let is_beach = biome.has_beaches() && terrain_height > sea_level - 2.0 && terrain_height < sea_level + 3.0;
+
+        if y < terrain_height - 2.0 {
+            // Underground: stone
+            blocks.stone
+        } else if y < terrain_height {
+            // Near surface
+            if is_beach || (biome.has_beaches() && terrain_height < water_level + 1.0) {
+                blocks.sand // Sand near water
+            } else {
+                biome.filler_block(blocks)
+            }
+        } else if y < terrain_height + 1.0 {
+            // Surface layer
+            if y < water_level && terrain_height < water_level {
+                blocks.water // Water
+            } else if is_beach {
+                blocks.sand // Sand
+            } else {
+                biome.top_block(blocks)
+            }
+        } else if y < water_level {
+            // Fill with water up to sea level, or the lake surface
+            blocks.water
+        } else {
+            BlockId::AIR
+        }
+    }
+
+    /// Trees and other features reaching into the chunk at `chunk_pos`
+    fn decorations(&self, chunk_pos: ChunkPos, params: &TerrainParams) -> Vec<PlacedFeature> {
+        features::features_for_chunk(
+            chunk_pos,
+            params,
+            |x, z, params| Column {
+                x,
+                z,
+                height: self.height(x, z, params),
+                biome: self.biome(x, z, params),
+                water_level: self.water_level(x, z, params),
+            },
+        )
+    }
+}
+
+/// Mountains, hills, plateaus, caves, rivers, lakes and trees across the climate biomes
+pub struct StandardGenerator;
+
+impl WorldGenerator for StandardGenerator {}
+
+/// Level grassland at `ground_height` above sea level, keeping caves and trees
+pub struct FlatGenerator {
+    pub ground_height: f32,
+}
+
+impl WorldGenerator for FlatGenerator {
+    fn height(&self, _x: f32, _z: f32, params: &TerrainParams) -> f32 {
+        params.sea_level + self.ground_height
+    }
+
+    fn biome(&self, _x: f32, _z: f32, _params: &TerrainParams) -> Biome {
+        Biome::Plains
+    }
+
+    fn water_level(&self, _x: f32, _z: f32, params: &TerrainParams) -> f32 {
+        params.sea_level
+    }
+}
+
+/// A grass top at y = 0 over `dirt_depth` layers of dirt, stone below that.
+/// No water, caves or trees.
+pub struct SuperflatGenerator {
+    pub dirt_depth: i32,
+}
+
+impl WorldGenerator for SuperflatGenerator {
+    fn height(&self, _x: f32, _z: f32, _params: &TerrainParams) -> f32 {
+        0.0
+    }
+
+    fn density(&self, _x: f32, _y: f32, _z: f32, _params: &TerrainParams) -> f32 {
+        f32::MIN
+    }
+
+    fn water_level(&self, _x: f32, _z: f32, params: &TerrainParams) -> f32 {
+        params.sea_level
+    }
+
+    fn surface_block(&self, column: Column, y: f32, _params: &TerrainParams, blocks: &TerrainBlocks) -> BlockId {
+        if y > column.height {
+            BlockId::AIR
+        } else if y == column.height {
+            blocks.grass
+        } else if y >= column.height - self.dirt_depth as f32 {
+            blocks.dirt
+        } else {
+            blocks.stone
+        }
+    }
+
+    fn decorations(&self, _chunk_pos: ChunkPos, _params: &TerrainParams) -> Vec<PlacedFeature> {
+        Vec::new()
+    }
+}
+
+/// The standard terrain with everything above sea level stretched `scale` times higher
+pub struct AmplifiedGenerator {
+    pub scale: f32,
+}
+
+impl WorldGenerator for AmplifiedGenerator {
+    fn relief(&self, x: f32, z: f32, params: &TerrainParams) -> f32 {
+        let height = StandardGenerator.relief(x, z, params);
+        if height > params.sea_level {
+            params.sea_level + (height - params.sea_level) * self.scale
+        } else {
+            height
+        }
+    }
+
+    fn height_range(&self) -> (i32, i32) {
+        let (min, max) = DEFAULT_HEIGHT_RANGE;
+        (min, (max as f32 * self.scale.max(1.0)).ceil() as i32)
+    }
+}
+
+/// Scattered islands rising from an ocean floor `ocean_depth` below sea level
+pub struct IslandsGenerator {
+    pub ocean_depth: f32,
+}
+
+impl WorldGenerator for IslandsGenerator {
+    fn relief(&self, x: f32, z: f32, params: &TerrainParams) -> f32 {
+        // Low frequency mask: land where it rises above 0.1, fading out into the ocean
+        let mask = noise::perlin2d(params.seed_hash().wrapping_add(5), x * 0.004, z * 0.004);
+        let land = ((mask - 0.1) * 4.0).clamp(0.0, 1.0);
+        let shape = land * land * (3.0 - 2.0 * land);
+
+        let land_height = StandardGenerator.relief(x, z, params).abs() * 0.5 + 6.0;
+        params.sea_level - self.ocean_depth + shape * (self.ocean_depth + land_height)
+    }
+}
+
+// Blocks the 3D noise of `DensityGenerator` can move the surface at full overhang strength
+const OVERHANG_REACH: f32 = 24.0;
+// Floating islands form in a band this far above sea level, `ISLAND_THICKNESS` up and down
+const ISLAND_ALTITUDE: f32 = 80.0;
+const ISLAND_THICKNESS: f32 = 12.0;
+
+/// True 3D terrain: the standard heightmap as a density gradient, disturbed by 3D noise
+/// into cliffs, overhangs and arches (`overhang_strength`), plus floating islands in a
+/// band above the land (`floating_island_frequency`)
+pub struct DensityGenerator;
+
+impl DensityGenerator {
+    // Highest solid block of a column, the ground trees grow on
+    fn surface_height(&self, x: f32, z: f32, params: &TerrainParams) -> f32 {
+        let height = self.height(x, z, params);
+        let bottom = height - OVERHANG_REACH - 1.0;
+        let mut y = self.ceiling(height, params).ceil();
+        while y > bottom {
+            if self.solidity(x, y, z, height, params) > 0.0 {
+                return y;
+            }
+            y -= 1.0;
+        }
+        height
+    }
+}
+
+impl WorldGenerator for DensityGenerator {
+    fn is_volumetric(&self) -> bool {
+        true
+    }
+
+    fn solidity(&self, x: f32, y: f32, z: f32, height: f32, params: &TerrainParams) -> f32 {
+        let strength = params.overhang_strength.max(0.0);
+        let gradient = (height + 1.0 - y) / OVERHANG_REACH;
+
+        // The noise stays within [-1, 1], so far enough below the surface is always solid
+        if gradient > strength {
+            return gradient;
+        }
+        let fbm = Fbm { octaves: 3, lacunarity: 2.0, gain: 0.5 };
+        let seed = params.seed_hash();
+        let mut solidity = gradient;
+        if gradient > -strength {
+            solidity += fbm.sample3d(seed.wrapping_add(6), x * 0.02, y * 0.03, z * 0.02) * strength;
+        }
+
+        let frequency = params.floating_island_frequency;
+        let band = (y - params.sea_level - ISLAND_ALTITUDE) / ISLAND_THICKNESS;
+        if frequency > 0.0 && band.abs() < 1.0 {
+            // Blobs of noise that thin out towards the edges of the band
+            let blob = noise::perlin3d(seed.wrapping_add(7), x * 0.02, y * 0.05, z * 0.02);
+            let island = blob - 0.5 + frequency.min(1.0) * 0.5 - band * band * 0.5;
+            solidity = solidity.max(island);
+        }
+        solidity
+    }
+
+    fn ceiling(&self, height: f32, params: &TerrainParams) -> f32 {
+        let terrain = height + 1.0 + OVERHANG_REACH * params.overhang_strength.max(0.0);
+        if params.floating_island_frequency > 0.0 {
+            terrain.max(params.sea_level + ISLAND_ALTITUDE + ISLAND_THICKNESS)
+        } else {
+            terrain
+        }
+    }
+
+    fn decorations(&self, chunk_pos: ChunkPos, params: &TerrainParams) -> Vec<PlacedFeature> {
+        features::features_for_chunk(
+            chunk_pos,
+            params,
+            |x, z, params| Column {
+                x,
+                z,
+                height: self.surface_height(x, z, params),
+                biome: self.biome(x, z, params),
+                water_level: self.water_level(x, z, params),
+            },
+        )
+    }
+}
+
+/// World generators by name
+pub struct WorldGenerators {
+    generators: HashMap<String, Box<dyn WorldGenerator>>,
+}
+
+impl Default for WorldGenerators {
+    /// The built-in `default`, `flat`, `superflat`, `amplified`, `islands` and `density` generators
+    fn default() -> Self {
+        let mut generators = Self {
+            generators: HashMap::new(),
+        };
+        generators.register(DEFAULT_GENERATOR, Box::new(StandardGenerator));
+        generators.register("flat", Box::new(FlatGenerator { ground_height: 8.0 }));
+        generators.register("superflat", Box::new(SuperflatGenerator { dirt_depth: 3 }));
+        generators.register("amplified", Box::new(AmplifiedGenerator { scale: 2.5 }));
+        generators.register("islands", Box::new(IslandsGenerator { ocean_depth: 12.0 }));
+        generators.register("density", Box::new(DensityGenerator));
+        generators
+    }
+}
+
+impl WorldGenerators {
+    /// Adds a generator, replacing any registered under the same name
+    pub fn register(&mut self, name: &str, generator: Box<dyn WorldGenerator>) {
+        self.generators.insert(name.to_string(), generator);
+    }
+
+    pub fn get(&self, name: &str) -> Option<&dyn WorldGenerator> {
+        self.generators.get(name).map(Box::as_ref)
+    }
+
+    /// Registered names in alphabetical order
+    pub fn names(&self) -> Vec<String> {
+        let mut names: Vec<String> = self.generators.keys().cloned().collect();
+        names.sort();
+        names
+    }
+}
@@ -2,18 +2,35 @@ mod geometry;
 
 use wasm_bindgen::prelude::*;
 use geometry::{AnimationState};
-use crate::geometry::terrain::TerrainParams;
+use crate::geometry::terrain::{CacheLimits, ChunkMeshDiff, GenerationBudget, RaycastHit, ViewFrustum};
+use crate::geometry::blocks::BlockRegistry;
+use crate::geometry::voxel::{ChunkMesh, MeshBuffers, MeshingMode, TextureAtlas};
+use std::collections::HashMap;
 use serde_wasm_bindgen::from_value;
 
+// Everything a custom `WorldGenerator` implementation needs
+pub use crate::geometry::blocks::{BlockId, TerrainBlocks};
+pub use crate::geometry::climate::{Biome, BiomeDefinition, Climate, Vegetation};
+pub use crate::geometry::features::{Feature, PlacedFeature};
+pub use crate::geometry::terrain::TerrainParams;
+pub use crate::geometry::worldgen::{Column, WorldGenerator};
+
 #[wasm_bindgen]
 pub struct GeometryEngine {
     animation_state: AnimationState,
 }
 
+impl Default for GeometryEngine {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
 #[wasm_bindgen]
 impl GeometryEngine {
     #[wasm_bindgen(constructor)]
     pub fn new() -> Self {
+        #[cfg(target_arch = "wasm32")]
         web_sys::console::log_1(&"GeometryEngine initialized".into());
         Self {
             animation_state: AnimationState::new(),
@@ -26,22 +43,263 @@ impl GeometryEngine {
         self.animation_state.generate_geometry()
     }
     
+    /// Per-chunk variant of `generate_frame` that only returns what changed since the last call
+    #[wasm_bindgen]
+    pub fn generate_chunk_meshes(&mut self, camera_x: f32, camera_y: f32, camera_z: f32, radius: f32) -> ChunkMeshUpdate {
+        self.animation_state.update_camera(camera_x, camera_y, camera_z, radius);
+        self.animation_state.generate_chunk_meshes()
+    }
+    
+    /// Only select chunks that can intersect the camera's view volume. Chunks within the
+    /// `radius` passed to `generate_frame` are always kept, even behind the camera.
+    #[wasm_bindgen]
+    #[allow(clippy::too_many_arguments)]
+    pub fn set_view_frustum(
+        &mut self,
+        direction_x: f32,
+        direction_y: f32,
+        direction_z: f32,
+        fov_degrees: f32,
+        aspect: f32,
+        near: f32,
+        far: f32,
+    ) {
+        self.animation_state.set_view_frustum(Some(ViewFrustum {
+            direction: [direction_x, direction_y, direction_z],
+            fov_degrees,
+            aspect,
+            near,
+            far,
+        }));
+    }
+    
+    /// Go back to selecting every chunk within the render distance sphere
+    #[wasm_bindgen]
+    pub fn clear_view_frustum(&mut self) {
+        self.animation_state.set_view_frustum(None);
+    }
+    
     #[wasm_bindgen]
     pub fn set_render_distance(&mut self, distance: i32) {
         self.animation_state.set_render_distance(distance);
     }
     
+    /// Vertical block range loaded in every visible column, `max_height` exclusive
+    #[wasm_bindgen]
+    pub fn set_world_height_range(&mut self, min_height: i32, max_height: i32) {
+        self.animation_state.set_world_height_range(min_height, max_height);
+    }
+    
     #[wasm_bindgen]
     pub fn set_terrain_params(&mut self, params_js: JsValue) -> Result<(), JsValue> {
         let mut params: TerrainParams = from_value(params_js)?;
         
         // Convert percentages to fractions
         params.cave_threshold /= 100.0;
-        params.desert_threshold /= 100.0;
+        params.temperature /= 100.0;
+        params.humidity /= 100.0;
+        params.overhang_strength /= 100.0;
+        params.floating_island_frequency /= 100.0;
         
         self.animation_state.set_terrain_params(params);
         Ok(())
     }
+    
+    /// Limit how many new chunks a single frame call may generate, by count and/or
+    /// milliseconds (0 = unlimited). Chunks nearest the camera are generated first and
+    /// the rest are reported by `pending_chunk_count` until a later call gets to them.
+    #[wasm_bindgen]
+    pub fn set_generation_budget(&mut self, max_chunks: usize, max_millis: f64) {
+        self.animation_state.set_generation_budget(GenerationBudget {
+            max_chunks,
+            max_millis,
+        });
+    }
+    
+    #[wasm_bindgen]
+    pub fn pending_chunk_count(&self) -> usize {
+        self.animation_state.pending_chunk_count()
+    }
+    
+    /// Limit the chunk cache: chunks beyond `unload_distance` chunks from the camera are
+    /// dropped, then least recently used ones until under `max_chunks` and `max_bytes`.
+    /// A budget of 0 means unlimited.
+    #[wasm_bindgen]
+    pub fn set_cache_limits(&mut self, unload_distance: i32, max_chunks: usize, max_bytes: usize) {
+        self.animation_state.set_cache_limits(CacheLimits {
+            unload_distance,
+            max_chunks,
+            max_bytes,
+        });
+    }
+    
+    #[wasm_bindgen]
+    pub fn cached_chunk_count(&self) -> usize {
+        self.animation_state.cached_chunk_count()
+    }
+    
+    /// Approximate memory held by cached chunks and their meshes
+    #[wasm_bindgen]
+    pub fn cache_size_bytes(&self) -> usize {
+        self.animation_state.cache_size_bytes()
+    }
+    
+    /// Block type id at a world block position, generating the chunk if needed
+    #[wasm_bindgen]
+    pub fn get_block_at(&mut self, x: i32, y: i32, z: i32) -> u32 {
+        self.animation_state.get_block_at(x, y, z).0 as u32
+    }
+    
+    /// Places a block type id at a world block position (0 = air to dig).
+    /// The chunk and any touching neighbour are re-meshed on the next frame.
+    #[wasm_bindgen]
+    pub fn set_block_at(&mut self, x: i32, y: i32, z: i32, block: u32) {
+        self.animation_state.set_block_at(x, y, z, BlockId::from_u32(block));
+    }
+    
+    /// First non-air block along a ray through loaded chunks, for picking and digging.
+    /// With `skip_non_solid` the ray passes through non-solid blocks such as water.
+    /// `max_distance` may be `Infinity`; rays end after 1024 blocks either way.
+    #[wasm_bindgen]
+    #[allow(clippy::too_many_arguments)]
+    pub fn raycast(
+        &self,
+        origin_x: f32,
+        origin_y: f32,
+        origin_z: f32,
+        direction_x: f32,
+        direction_y: f32,
+        direction_z: f32,
+        max_distance: f32,
+        skip_non_solid: bool,
+    ) -> Option<BlockHit> {
+        self.animation_state
+            .raycast([origin_x, origin_y, origin_z], [direction_x, direction_y, direction_z], max_distance, skip_non_solid)
+            .map(BlockHit::from)
+    }
+    
+    /// Number of player-edited blocks kept across regeneration
+    #[wasm_bindgen]
+    pub fn edit_count(&self) -> usize {
+        self.animation_state.edit_count()
+    }
+    
+    /// Discards all player edits, restoring the generated terrain
+    #[wasm_bindgen]
+    pub fn clear_edits(&mut self) {
+        self.animation_state.clear_edits();
+    }
+    
+    /// Encodes the world, seed and terrain parameters into region files, e.g. for IndexedDB.
+    /// Only edited chunks are stored, and load with the ground they were saved with; the
+    /// rest regenerates from the seed.
+    #[wasm_bindgen]
+    pub fn save_world(&self) -> WorldSave {
+        WorldSave {
+            regions: self.animation_state.save_regions().into_iter().collect(),
+        }
+    }
+    
+    /// Restores one region file produced by `save_world`, adopting its terrain parameters.
+    /// Call `clear_edits` first when switching to a different world.
+    #[wasm_bindgen]
+    pub fn load_region(&mut self, bytes: &[u8]) -> Result<(), JsValue> {
+        self.animation_state
+            .load_region(bytes)
+            .map_err(|err| JsValue::from_str(&err.to_string()))
+    }
+    
+    /// Replaces the block definitions with a JSON registry of the form
+    /// `{ "blocks": [{ "id": 1, "name": "stone", "color": [0.5, 0.5, 0.5] }, ...] }`.
+    /// Optional per-block fields: `transparent`, `solid`, `lightEmission`, and `textures`
+    /// with atlas tile indices for `all`, `top`, `bottom` and `side` faces.
+    #[wasm_bindgen]
+    pub fn set_block_registry(&mut self, source: &str) -> Result<(), JsValue> {
+        let registry = BlockRegistry::from_json(source).map_err(|err| JsValue::from_str(&err))?;
+        self.animation_state.set_block_registry(registry);
+        Ok(())
+    }
+    
+    /// Same as `set_block_registry` for a TOML registry with a `[[blocks]]` array
+    #[wasm_bindgen]
+    pub fn set_block_registry_toml(&mut self, source: &str) -> Result<(), JsValue> {
+        let registry = BlockRegistry::from_toml(source).map_err(|err| JsValue::from_str(&err))?;
+        self.animation_state.set_block_registry(registry);
+        Ok(())
+    }
+    
+    /// Id of a block by its registry name, e.g. to place blocks picked in the UI
+    #[wasm_bindgen]
+    pub fn block_id(&self, name: &str) -> Option<u32> {
+        self.animation_state.block_id(name).map(|id| id.0 as u32)
+    }
+    
+    /// Switch between naive per-face meshing and greedy quad merging
+    #[wasm_bindgen]
+    pub fn set_greedy_meshing(&mut self, enabled: bool) {
+        let mode = if enabled { MeshingMode::Greedy } else { MeshingMode::Naive };
+        self.animation_state.set_meshing_mode(mode);
+    }
+    
+    /// Emit texture coordinates into an atlas of `columns` x `rows` equally sized tiles,
+    /// numbered row by row from the top left. See `GeometryData::uvs` for sampling.
+    #[wasm_bindgen]
+    pub fn set_texture_atlas(&mut self, columns: u32, rows: u32) {
+        self.animation_state.set_texture_atlas(Some(TextureAtlas::new(columns, rows)));
+    }
+    
+    /// Stop emitting texture coordinates and colour every block flat again
+    #[wasm_bindgen]
+    pub fn clear_texture_atlas(&mut self) {
+        self.animation_state.set_texture_atlas(None);
+    }
+    
+    /// Regenerate the world with the registered generator called `name`, keeping edits,
+    /// and switch to the world height range the generator needs.
+    /// Built in: `default`, `flat`, `superflat`, `amplified`, `islands` and `density`.
+    #[wasm_bindgen]
+    pub fn set_world_generator(&mut self, name: &str) -> Result<(), JsValue> {
+        if self.animation_state.set_world_generator(name) {
+            Ok(())
+        } else {
+            Err(JsValue::from_str(&format!("unknown world generator `{}`", name)))
+        }
+    }
+    
+    /// Name of the generator new chunks come from
+    #[wasm_bindgen]
+    pub fn world_generator(&self) -> String {
+        self.animation_state.world_generator().to_string()
+    }
+    
+    /// Names of all registered generators in alphabetical order
+    #[wasm_bindgen]
+    pub fn world_generator_names(&self) -> Vec<String> {
+        self.animation_state.world_generator_names()
+    }
+}
+
+// Extension points for Rust embedders, not exported to JavaScript
+impl GeometryEngine {
+    /// Makes a custom generator available to `set_world_generator` under `name`,
+    /// replacing any generator already registered with that name
+    pub fn register_world_generator(&mut self, name: &str, generator: Box<dyn WorldGenerator>) {
+        self.animation_state.register_world_generator(name, generator);
+    }
+}
+
+// File based persistence for native builds
+#[cfg(not(target_arch = "wasm32"))]
+impl GeometryEngine {
+    /// Writes the world as region files into `dir`, creating it if needed
+    pub fn save_world_to_dir(&self, dir: &std::path::Path) -> std::io::Result<()> {
+        self.animation_state.save_to_dir(dir)
+    }
+    
+    /// Loads every region file in `dir` written by `save_world_to_dir`
+    pub fn load_world_from_dir(&mut self, dir: &std::path::Path) -> std::io::Result<()> {
+        self.animation_state.load_from_dir(dir)
+    }
 }
 
 #[wasm_bindgen]
@@ -50,6 +308,11 @@ pub struct GeometryData {
     indices: Vec<u32>,
     normals: Vec<f32>,
     colors: Vec<f32>,
+    light: Vec<f32>,
+    uvs: Vec<f32>,
+    uv_tiles: Vec<f32>,
+    // Water, leaves and other transparent blocks, drawn blended after the opaque buffers
+    transparent: MeshBuffers,
 }
 
 #[wasm_bindgen]
@@ -60,6 +323,10 @@ impl GeometryData {
             indices,
             normals,
             colors,
+            light: Vec::new(),
+            uvs: Vec::new(),
+            uv_tiles: Vec::new(),
+            transparent: MeshBuffers::default(),
         }
     }
 
@@ -82,4 +349,196 @@ impl GeometryData {
     pub fn colors(&self) -> Vec<f32> {
         self.colors.clone()
     }
-}
\ No newline at end of file
+    
+    /// Sky and block light per vertex as flat `[sky, block, ...]` pairs from 0 to 1
+    #[wasm_bindgen(getter)]
+    pub fn light(&self) -> Vec<f32> {
+        self.light.clone()
+    }
+    
+    /// Texture coordinates in blocks as flat `[u, v, ...]` pairs, empty without an atlas.
+    /// They count past 1 across merged quads; sample the atlas at
+    /// `uv_tiles + fract(uvs) * tile size` so the texture repeats once per block.
+    #[wasm_bindgen(getter)]
+    pub fn uvs(&self) -> Vec<f32> {
+        self.uvs.clone()
+    }
+    
+    /// Lower left UV corner of each vertex's atlas tile as flat `[u, v, ...]` pairs,
+    /// negative for untextured faces
+    #[wasm_bindgen(getter)]
+    pub fn uv_tiles(&self) -> Vec<f32> {
+        self.uv_tiles.clone()
+    }
+    
+    #[wasm_bindgen(getter)]
+    pub fn transparent_vertices(&self) -> Vec<f32> {
+        self.transparent.vertices.clone()
+    }
+    
+    #[wasm_bindgen(getter)]
+    pub fn transparent_indices(&self) -> Vec<u32> {
+        self.transparent.indices.clone()
+    }
+    
+    #[wasm_bindgen(getter)]
+    pub fn transparent_normals(&self) -> Vec<f32> {
+        self.transparent.normals.clone()
+    }
+    
+    #[wasm_bindgen(getter)]
+    pub fn transparent_colors(&self) -> Vec<f32> {
+        self.transparent.colors.clone()
+    }
+    
+    #[wasm_bindgen(getter)]
+    pub fn transparent_light(&self) -> Vec<f32> {
+        self.transparent.light.clone()
+    }
+    
+    #[wasm_bindgen(getter)]
+    pub fn transparent_uvs(&self) -> Vec<f32> {
+        self.transparent.uvs.clone()
+    }
+    
+    #[wasm_bindgen(getter)]
+    pub fn transparent_uv_tiles(&self) -> Vec<f32> {
+        self.transparent.uv_tiles.clone()
+    }
+}
+
+impl From<ChunkMesh> for GeometryData {
+    fn from(mesh: ChunkMesh) -> Self {
+        let opaque = mesh.opaque;
+        Self {
+            light: opaque.light,
+            uvs: opaque.uvs,
+            uv_tiles: opaque.uv_tiles,
+            transparent: mesh.transparent,
+            ..Self::new(opaque.vertices, opaque.indices, opaque.normals, opaque.colors)
+        }
+    }
+}
+
+#[wasm_bindgen]
+pub struct ChunkMeshUpdate {
+    added: Vec<i32>,
+    changed: Vec<i32>,
+    removed: Vec<i32>,
+    meshes: HashMap<(i32, i32, i32), GeometryData>,
+    pending: usize,
+}
+
+#[wasm_bindgen]
+impl ChunkMeshUpdate {
+    /// Chunk coordinates of newly visible chunks as flat `[x, y, z, ...]` triples
+    #[wasm_bindgen(getter)]
+    pub fn added(&self) -> Vec<i32> {
+        self.added.clone()
+    }
+
+    /// Chunk coordinates of chunks whose mesh was rebuilt as flat `[x, y, z, ...]` triples
+    #[wasm_bindgen(getter)]
+    pub fn changed(&self) -> Vec<i32> {
+        self.changed.clone()
+    }
+
+    /// Chunk coordinates of chunks to drop as flat `[x, y, z, ...]` triples
+    #[wasm_bindgen(getter)]
+    pub fn removed(&self) -> Vec<i32> {
+        self.removed.clone()
+    }
+
+    /// Visible chunks not generated yet because of the generation budget
+    #[wasm_bindgen(getter)]
+    pub fn pending(&self) -> usize {
+        self.pending
+    }
+
+    /// Moves the mesh of an added or changed chunk out of the update
+    pub fn take_mesh(&mut self, chunk_x: i32, chunk_y: i32, chunk_z: i32) -> Option<GeometryData> {
+        self.meshes.remove(&(chunk_x, chunk_y, chunk_z))
+    }
+}
+
+impl From<ChunkMeshDiff> for ChunkMeshUpdate {
+    fn from(diff: ChunkMeshDiff) -> Self {
+        let flatten = |positions: Vec<(i32, i32, i32)>| {
+            positions.into_iter().flat_map(|(x, y, z)| [x, y, z]).collect()
+        };
+        Self {
+            added: flatten(diff.added),
+            changed: flatten(diff.changed),
+            removed: flatten(diff.removed),
+            meshes: diff.meshes.into_iter()
+                .map(|(pos, mesh)| (pos, GeometryData::from(mesh)))
+                .collect(),
+            pending: diff.pending,
+        }
+    }
+}
+
+#[wasm_bindgen]
+pub struct BlockHit {
+    hit: RaycastHit,
+}
+
+#[wasm_bindgen]
+impl BlockHit {
+    /// World position of the hit block as `[x, y, z]`
+    #[wasm_bindgen(getter)]
+    pub fn position(&self) -> Vec<i32> {
+        let (x, y, z) = self.hit.block_pos;
+        vec![x, y, z]
+    }
+
+    /// Block type id of the hit block
+    #[wasm_bindgen(getter)]
+    pub fn block(&self) -> u32 {
+        self.hit.block.0 as u32
+    }
+
+    /// Normal of the face the ray entered through as `[x, y, z]`
+    #[wasm_bindgen(getter)]
+    pub fn normal(&self) -> Vec<i32> {
+        let (x, y, z) = self.hit.face.normal();
+        vec![x, y, z]
+    }
+
+    /// Empty cell in front of the hit face, where a placed block goes, as `[x, y, z]`
+    #[wasm_bindgen(getter)]
+    pub fn place_position(&self) -> Vec<i32> {
+        let (x, y, z) = self.hit.place_pos;
+        vec![x, y, z]
+    }
+
+    #[wasm_bindgen(getter)]
+    pub fn distance(&self) -> f32 {
+        self.hit.distance
+    }
+}
+
+impl From<RaycastHit> for BlockHit {
+    fn from(hit: RaycastHit) -> Self {
+        Self { hit }
+    }
+}
+
+#[wasm_bindgen]
+pub struct WorldSave {
+    regions: HashMap<(i32, i32, i32), Vec<u8>>,
+}
+
+#[wasm_bindgen]
+impl WorldSave {
+    /// Region coordinates as flat `[x, y, z, ...]` triples
+    #[wasm_bindgen(getter)]
+    pub fn region_positions(&self) -> Vec<i32> {
+        self.regions.keys().flat_map(|&(x, y, z)| [x, y, z]).collect()
+    }
+
+    /// Moves the encoded bytes of one region out of the save
+    pub fn take_region(&mut self, region_x: i32, region_y: i32, region_z: i32) -> Option<Vec<u8>> {
+        self.regions.remove(&(region_x, region_y, region_z))
+    }
+}
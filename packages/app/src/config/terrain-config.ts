@@ -37,6 +37,33 @@ export const TERRAIN_PARAMS: TerrainParam[] = [
     step: 1,
     rustField: 'roughness'
   },
+  {
+    id: 'octaves',
+    label: 'Noise Octaves',
+    min: 1,
+    max: 8,
+    default: 4,
+    step: 1,
+    rustField: 'octaves'
+  },
+  {
+    id: 'lacunarity',
+    label: 'Lacunarity',
+    min: 1.5,
+    max: 3,
+    default: 2,
+    step: 0.1,
+    rustField: 'lacunarity'
+  },
+  {
+    id: 'gain',
+    label: 'Octave Gain',
+    min: 0.2,
+    max: 0.8,
+    default: 0.5,
+    step: 0.05,
+    rustField: 'gain'
+  },
   {
     id: 'sea-level',
     label: 'Sea Level',
@@ -76,14 +103,53 @@ export const TERRAIN_PARAMS: TerrainParam[] = [
     rustField: 'biomeScale'
   },
   {
-    id: 'desert-threshold',
-    label: 'Desert Amount',
+    id: 'temperature',
+    label: 'Temperature',
+    min: -100,
+    max: 100,
+    default: 0,
+    step: 10,
+    unit: '%',
+    rustField: 'temperature'
+  },
+  {
+    id: 'humidity',
+    label: 'Humidity',
+    min: -100,
+    max: 100,
+    default: 0,
+    step: 10,
+    unit: '%',
+    rustField: 'humidity'
+  },
+  {
+    id: 'overhang-strength',
+    label: 'Overhang Strength',
+    min: 0,
+    max: 100,
+    default: 50,
+    step: 10,
+    unit: '%',
+    rustField: 'overhangStrength'
+  },
+  {
+    id: 'floating-islands',
+    label: 'Floating Islands',
     min: 0,
     max: 100,
     default: 30,
     step: 10,
     unit: '%',
-    rustField: 'desertThreshold'
+    rustField: 'floatingIslandFrequency'
+  },
+  {
+    id: 'seed',
+    label: 'World Seed',
+    min: 0,
+    max: 1000,
+    default: 0,
+    step: 1,
+    rustField: 'seed'
   }
 ];
 
@@ -91,11 +157,18 @@ export interface TerrainParams {
   mountainScale: number;
   hillsScale: number;
   roughness: number;
+  octaves: number;
+  lacunarity: number;
+  gain: number;
   seaLevel: number;
   treeDensity: number;
   caveThreshold: number;
   biomeScale: number;
-  desertThreshold: number;
+  temperature: number;
+  humidity: number;
+  overhangStrength: number;
+  floatingIslandFrequency: number;
+  seed: number;
 }
 
 export function getDefaultParams(): TerrainParams {
@@ -91,6 +91,12 @@ export class GeometryControllerAsync {
         this.latestGeometry.normals,
         this.latestGeometry.colors
       );
+      this.sceneManager.updateTransparentGeometry(
+        this.latestGeometry.transparentVertices,
+        this.latestGeometry.transparentIndices,
+        this.latestGeometry.transparentNormals,
+        this.latestGeometry.transparentColors
+      );
       this.latestGeometry = null; // Clear after applying
     }
   }
@@ -7,6 +7,7 @@ export class SceneManager {
   private renderer: THREE.WebGLRenderer;
   private controls: PointerLockControls;
   private geometryMesh: THREE.Mesh | null = null;
+  private transparentMesh: THREE.Mesh | null = null;
   private keys: { [key: string]: boolean } = {};
   private moveSpeed: number = 0.1;
   // private velocity: THREE.Vector3 = new THREE.Vector3();
@@ -161,6 +162,42 @@ export class SceneManager {
     this.scene.add(this.geometryMesh);
   }
 
+  // Blended pass for water and leaves, drawn after the opaque terrain without writing depth
+  updateTransparentGeometry(vertices: Float32Array, indices: Uint32Array, normals: Float32Array, colors: Float32Array): void {
+    if (this.transparentMesh) {
+      this.scene.remove(this.transparentMesh);
+      this.transparentMesh.geometry.dispose();
+      if (this.transparentMesh.material instanceof THREE.Material) {
+        this.transparentMesh.material.dispose();
+      }
+      this.transparentMesh = null;
+    }
+    if (indices.length === 0) {
+      return;
+    }
+
+    const geometry = new THREE.BufferGeometry();
+    geometry.setAttribute('position', new THREE.BufferAttribute(vertices, 3));
+    geometry.setAttribute('normal', new THREE.BufferAttribute(normals, 3));
+    geometry.setAttribute('color', new THREE.BufferAttribute(colors, 3));
+    geometry.setIndex(new THREE.BufferAttribute(indices, 1));
+
+    const material = new THREE.MeshPhongMaterial({
+      vertexColors: true,
+      specular: 0x111111,
+      shininess: 100,
+      transparent: true,
+      opacity: 0.6,
+      depthWrite: false,
+      side: THREE.DoubleSide
+    });
+
+    this.transparentMesh = new THREE.Mesh(geometry, material);
+    this.transparentMesh.renderOrder = 1;
+    this.transparentMesh.receiveShadow = true;
+    this.scene.add(this.transparentMesh);
+  }
+
   render(): void {
     this.updateMovement();
     this.renderer.render(this.scene, this.camera);
@@ -4,11 +4,18 @@ export interface TerrainParams {
   mountainScale: number;
   hillsScale: number;
   roughness: number;
+  octaves: number;
+  lacunarity: number;
+  gain: number;
   seaLevel: number;
   treeDensity: number;
   caveThreshold: number;
   biomeScale: number;
-  desertThreshold: number;
+  temperature: number;
+  humidity: number;
+  overhangStrength: number;
+  floatingIslandFrequency: number;
+  seed: number;
 }
 
 export interface GeometryMessage {
@@ -26,6 +33,11 @@ export interface GeometryResult {
   indices: Uint32Array;
   normals: Float32Array;
   colors: Float32Array;
+  // Water and leaves, drawn blended after the opaque buffers
+  transparentVertices: Float32Array;
+  transparentIndices: Uint32Array;
+  transparentNormals: Float32Array;
+  transparentColors: Float32Array;
   stats: {
     vertices: number;
     triangles: number;
@@ -33,6 +45,21 @@ export interface GeometryResult {
   timestamp: number;
 }
 
+// Darkest a vertex gets with neither sky nor block light reaching it
+const MIN_LIGHT = 0.15;
+
+// Scales vertex colours in place by the brighter of each vertex's sky and block light
+function applyLight(colors: Float32Array, light: Float32Array): Float32Array {
+  for (let vertex = 0; vertex < light.length / 2; vertex++) {
+    const level = Math.max(light[vertex * 2], light[vertex * 2 + 1]);
+    const brightness = MIN_LIGHT + (1 - MIN_LIGHT) * level;
+    colors[vertex * 3] *= brightness;
+    colors[vertex * 3 + 1] *= brightness;
+    colors[vertex * 3 + 2] *= brightness;
+  }
+  return colors;
+}
+
 class GeometryWorkerManager {
   private generator: GeometryGenerator | null = null;
   private isRunning = false;
@@ -81,16 +108,27 @@ class GeometryWorkerManager {
       const vertices = new Float32Array(geometryData.vertices);
       const indices = new Uint32Array(geometryData.indices);
       const normals = new Float32Array(geometryData.normals);
-      const colors = new Float32Array(geometryData.colors);
+      const colors = applyLight(new Float32Array(geometryData.colors), new Float32Array(geometryData.light));
+      const transparentVertices = new Float32Array(geometryData.transparent_vertices);
+      const transparentIndices = new Uint32Array(geometryData.transparent_indices);
+      const transparentNormals = new Float32Array(geometryData.transparent_normals);
+      const transparentColors = applyLight(
+        new Float32Array(geometryData.transparent_colors),
+        new Float32Array(geometryData.transparent_light)
+      );
       
       const result: GeometryResult = {
         vertices,
         indices,
         normals,
         colors,
+        transparentVertices,
+        transparentIndices,
+        transparentNormals,
+        transparentColors,
         stats: {
-          vertices: vertices.length / 3,
-          triangles: indices.length / 3
+          vertices: (vertices.length + transparentVertices.length) / 3,
+          triangles: (indices.length + transparentIndices.length) / 3
         },
         timestamp: performance.now()
       };
@@ -100,7 +138,11 @@ class GeometryWorkerManager {
         vertices.buffer,
         indices.buffer,
         normals.buffer,
-        colors.buffer
+        colors.buffer,
+        transparentVertices.buffer,
+        transparentIndices.buffer,
+        transparentNormals.buffer,
+        transparentColors.buffer
       ]);
     }
   }
@@ -1,4 +1,4 @@
-import init, { GeometryEngine, GeometryData } from 'geometry-engine';
+import init, { GeometryEngine, GeometryData, ChunkMeshUpdate, BlockHit, WorldSave } from 'geometry-engine';
 
 let wasmInitialized = false;
 let initPromise: Promise<void> | null = null;
@@ -31,6 +31,36 @@ export class GeometryGenerator {
     return this.engine.generate_frame(cameraX, cameraY, cameraZ, radius);
   }
   
+  generateChunkMeshes(cameraX: number, cameraY: number, cameraZ: number, radius: number): ChunkMeshUpdate | null {
+    if (!this.engine) {
+      console.error('GeometryEngine not initialized');
+      return null;
+    }
+    return this.engine.generate_chunk_meshes(cameraX, cameraY, cameraZ, radius);
+  }
+  
+  setViewFrustum(
+    direction: { x: number; y: number; z: number },
+    fovDegrees: number,
+    aspect: number,
+    near: number,
+    far: number
+  ): void {
+    if (!this.engine) {
+      console.error('GeometryEngine not initialized');
+      return;
+    }
+    this.engine.set_view_frustum(direction.x, direction.y, direction.z, fovDegrees, aspect, near, far);
+  }
+  
+  clearViewFrustum(): void {
+    if (!this.engine) {
+      console.error('GeometryEngine not initialized');
+      return;
+    }
+    this.engine.clear_view_frustum();
+  }
+  
   setRenderDistance(distance: number): void {
     if (!this.engine) {
       console.error('GeometryEngine not initialized');
@@ -39,6 +69,14 @@ export class GeometryGenerator {
     this.engine.set_render_distance(distance);
   }
   
+  setWorldHeightRange(minHeight: number, maxHeight: number): void {
+    if (!this.engine) {
+      console.error('GeometryEngine not initialized');
+      return;
+    }
+    this.engine.set_world_height_range(minHeight, maxHeight);
+  }
+  
   setTerrainParams(params: any): void {
     if (!this.engine) {
       console.error('GeometryEngine not initialized');
@@ -46,6 +84,157 @@ export class GeometryGenerator {
     }
     this.engine.set_terrain_params(params);
   }
+  
+  setGenerationBudget(maxChunks: number, maxMillis: number): void {
+    if (!this.engine) {
+      console.error('GeometryEngine not initialized');
+      return;
+    }
+    this.engine.set_generation_budget(maxChunks, maxMillis);
+  }
+  
+  getPendingChunkCount(): number {
+    if (!this.engine) {
+      console.error('GeometryEngine not initialized');
+      return 0;
+    }
+    return this.engine.pending_chunk_count();
+  }
+  
+  setCacheLimits(unloadDistance: number, maxChunks: number, maxBytes: number): void {
+    if (!this.engine) {
+      console.error('GeometryEngine not initialized');
+      return;
+    }
+    this.engine.set_cache_limits(unloadDistance, maxChunks, maxBytes);
+  }
+  
+  getCacheStats(): { chunks: number; bytes: number } | null {
+    if (!this.engine) {
+      console.error('GeometryEngine not initialized');
+      return null;
+    }
+    return {
+      chunks: this.engine.cached_chunk_count(),
+      bytes: this.engine.cache_size_bytes()
+    };
+  }
+  
+  getBlockAt(x: number, y: number, z: number): number {
+    if (!this.engine) {
+      console.error('GeometryEngine not initialized');
+      return 0;
+    }
+    return this.engine.get_block_at(x, y, z);
+  }
+  
+  setBlockAt(x: number, y: number, z: number, block: number): void {
+    if (!this.engine) {
+      console.error('GeometryEngine not initialized');
+      return;
+    }
+    this.engine.set_block_at(x, y, z, block);
+  }
+  
+  clearEdits(): void {
+    if (!this.engine) {
+      console.error('GeometryEngine not initialized');
+      return;
+    }
+    this.engine.clear_edits();
+  }
+
+  setBlockRegistry(source: string, format: 'json' | 'toml' = 'json'): void {
+    if (!this.engine) {
+      console.error('GeometryEngine not initialized');
+      return;
+    }
+    if (format === 'toml') {
+      this.engine.set_block_registry_toml(source);
+    } else {
+      this.engine.set_block_registry(source);
+    }
+  }
+
+  // Throws if no generator is registered under the name; also applies the generator's height range
+  setWorldGenerator(name: string): void {
+    if (!this.engine) {
+      console.error('GeometryEngine not initialized');
+      return;
+    }
+    this.engine.set_world_generator(name);
+  }
+
+  worldGenerators(): string[] {
+    if (!this.engine) {
+      console.error('GeometryEngine not initialized');
+      return [];
+    }
+    return this.engine.world_generator_names();
+  }
+
+  blockId(name: string): number | undefined {
+    if (!this.engine) {
+      console.error('GeometryEngine not initialized');
+      return undefined;
+    }
+    return this.engine.block_id(name);
+  }
+
+  saveWorld(): WorldSave | null {
+    if (!this.engine) {
+      console.error('GeometryEngine not initialized');
+      return null;
+    }
+    return this.engine.save_world();
+  }
+
+  loadRegion(bytes: Uint8Array): void {
+    if (!this.engine) {
+      console.error('GeometryEngine not initialized');
+      return;
+    }
+    this.engine.load_region(bytes);
+  }
+  
+  // First non-air block along the ray; skipNonSolid looks through water and other non-solid blocks
+  raycast(
+    origin: { x: number; y: number; z: number },
+    direction: { x: number; y: number; z: number },
+    maxDistance: number,
+    skipNonSolid = false
+  ): BlockHit | null {
+    if (!this.engine) {
+      console.error('GeometryEngine not initialized');
+      return null;
+    }
+    return this.engine.raycast(
+      origin.x, origin.y, origin.z,
+      direction.x, direction.y, direction.z,
+      maxDistance, skipNonSolid
+    ) ?? null;
+  }
+  
+  setGreedyMeshing(enabled: boolean): void {
+    if (!this.engine) {
+      console.error('GeometryEngine not initialized');
+      return;
+    }
+    this.engine.set_greedy_meshing(enabled);
+  }
+  
+  // Emit UVs into an atlas of columns x rows tiles; pass null to go back to flat colours
+  setTextureAtlas(atlas: { columns: number; rows: number } | null): void {
+    if (!this.engine) {
+      console.error('GeometryEngine not initialized');
+      return;
+    }
+    if (atlas) {
+      this.engine.set_texture_atlas(atlas.columns, atlas.rows);
+    } else {
+      this.engine.clear_texture_atlas();
+    }
+  }
 }
 
-export { GeometryEngine, GeometryData };
\ No newline at end of file
+export { GeometryEngine, GeometryData, ChunkMeshUpdate, BlockHit, WorldSave };
\ No newline at end of file